@@ -1,9 +1,18 @@
 // #![feature(in_band_lifetimes)]
 // #![feature(dec2flt)]
 
-use core::panic;
-use log::{error, LevelFilter};
+use log::{debug, error, LevelFilter};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::f32;
+use std::fmt::Write;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{
+    Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock, TryLockError, TryLockResult,
+};
+use std::time::Instant;
 
 extern crate dict_derive;
 use dict_derive::{FromPyObject, IntoPyObject};
@@ -11,663 +20,3364 @@ use dict_derive::{FromPyObject, IntoPyObject};
 extern crate pyo3;
 // use pyo3::create_exception;
 // use pyo3::exceptions::PyException;
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyRuntimeError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict, PyList, PyTuple};
 use pyo3::wrap_pyfunction;
 
 extern crate pyo3_log;
-use pyo3_log::{Caching, Logger};
+use pyo3_log::{Caching, Logger, ResetHandle};
+
+extern crate slotmap;
+use slotmap::{Key, KeyData};
 
 extern crate taffy;
+use taffy::error::TaffyError;
+use taffy::geometry::Point;
 use taffy::node::MeasureFunc;
 use taffy::prelude::*;
 
 // MAIN
 
+// The maximum number of times the layout is computed again to resolve calc
+// lengths, see `Tree::resolve_calc`
+const MAX_CALC_PASSES: usize = 8;
+
+struct Tree {
+    taffy: Taffy,
+    // Taffy panics when indexed with a node that is not in the tree, so the
+    // nodes are tracked here to be able to check a node before using it.
+    nodes: HashSet<Node>,
+    // Nodes with a measure function, which calls into Python
+    measured: HashSet<Node>,
+    // Taffy 0.3 keeps the unrounded layout private, so rounding is done here
+    // instead: taffy always computes unrounded layouts, and the rounded layouts
+    // are stored separately after each `node_compute_layout`.
+    use_rounding: bool,
+    rounding_scale: f32,
+    rounded_layouts: HashMap<Node, Layout>,
+    // See `set_direction`
+    rtl: bool,
+    // The calc lengths of the styles, see `resolve_calc`
+    calc: HashMap<Node, CalcStyle>,
+    // The grid line names of the styles, see `resolve_line_names`
+    line_names: HashMap<Node, LineNames>,
+    // The style of nodes created without a style, see `set_default_style`
+    default_style: (Style, StyleExtras),
+    // The available space of the last layout computed for each root, see
+    // `node_last_available_space`
+    available_spaces: HashMap<Node, Size<AvailableSpace>>,
+    // The rounding scale of the last layout computed with rounding enabled for
+    // each root, see `node_compute_layout_if_dirty`
+    rounding_scales: HashMap<Node, f32>,
+    // See `node_set_scroll_offset`
+    scroll_offsets: HashMap<Node, Point<f32>>,
+    // See `node_set_scroll_padding`
+    scroll_paddings: HashMap<Node, Rect<LengthPercentage>>,
+    // See `node_set_key`
+    keys: HashMap<Node, String>,
+}
+
+impl Tree {
+    fn new() -> Self {
+        let mut taffy = Taffy::new();
+        taffy.disable_rounding();
+        Tree {
+            taffy,
+            nodes: HashSet::new(),
+            measured: HashSet::new(),
+            use_rounding: true,
+            rounding_scale: 1.,
+            rounded_layouts: HashMap::new(),
+            rtl: false,
+            calc: HashMap::new(),
+            line_names: HashMap::new(),
+            default_style: (Style::DEFAULT, StyleExtras::default()),
+            available_spaces: HashMap::new(),
+            rounding_scales: HashMap::new(),
+            scroll_offsets: HashMap::new(),
+            scroll_paddings: HashMap::new(),
+            keys: HashMap::new(),
+        }
+    }
+
+    fn round(&self, value: f32) -> f32 {
+        round_to_scale(value, self.rounding_scale)
+    }
+
+    fn round_layout(&mut self, node: Node, cumulative_x: f32, cumulative_y: f32) {
+        let unrounded = *self.taffy.layout(node).unwrap();
+        let layout = rounded_layout(unrounded, cumulative_x, cumulative_y, self.rounding_scale);
+        self.rounded_layouts.insert(node, layout);
+        for child in self.taffy.children(node).unwrap() {
+            self.round_layout(
+                child,
+                cumulative_x + unrounded.location.x,
+                cumulative_y + unrounded.location.y,
+            );
+        }
+    }
+
+    fn stored_layout(&self, node: Node, rounded: bool) -> Layout {
+        match self.rounded_layouts.get(&node) {
+            Some(layout) if rounded => *layout,
+            _ => *self.taffy.layout(node).unwrap(),
+        }
+    }
+
+    // Returns the layout of the node with the location mirrored within the
+    // parent, if the direction is right-to-left.
+    fn directed_layout(&self, node: Node, rounded: bool) -> Layout {
+        let mut layout = self.stored_layout(node, rounded);
+        if self.rtl {
+            if let Some(parent) = self.taffy.parent(node) {
+                let parent_width = self.stored_layout(parent, rounded).size.width;
+                layout.location.x = parent_width - layout.location.x - layout.size.width;
+            }
+        }
+        layout
+    }
+
+    // Returns the rounded layout of the node, if it was computed with rounding
+    // enabled, otherwise the unrounded layout.
+    fn layout(&self, node: Node) -> Layout {
+        self.directed_layout(node, true)
+    }
+
+    fn unrounded_layout(&self, node: Node) -> Layout {
+        self.directed_layout(node, false)
+    }
+
+    fn absolute_layout(&self, node: Node) -> Layout {
+        let mut location = Point::ZERO;
+        let mut current = Some(node);
+        while let Some(n) = current {
+            let unrounded = self.unrounded_layout(n).location;
+            location.x += unrounded.x;
+            location.y += unrounded.y;
+            current = self.taffy.parent(n);
+            // The descendants of a scrolled node are moved by its scroll offset
+            if let Some(offset) = current.and_then(|parent| self.scroll_offsets.get(&parent)) {
+                location.x -= offset.x;
+                location.y -= offset.y;
+            }
+            // and its scroll padding
+            if let Some(parent) = current {
+                let padding = self.scroll_padding(parent);
+                location.x += padding.left;
+                location.y += padding.top;
+            }
+        }
+        let mut layout = self.layout(node);
+        if self.rounded_layouts.contains_key(&node) {
+            location.x = self.round(location.x);
+            location.y = self.round(location.y);
+        }
+        layout.location = location;
+        layout
+    }
+
+    // Returns the scroll padding of the node in points, with percentages resolved
+    // against the size of the node, see `node_set_scroll_padding`
+    fn scroll_padding(&self, node: Node) -> Rect<f32> {
+        let size = self.layout(node).size;
+        let resolve = |length, base| match length {
+            LengthPercentage::Points(value) => value,
+            LengthPercentage::Percent(value) => value * base,
+        };
+        match self.scroll_paddings.get(&node) {
+            Some(padding) => Rect {
+                left: resolve(padding.left, size.width),
+                right: resolve(padding.right, size.width),
+                top: resolve(padding.top, size.height),
+                bottom: resolve(padding.bottom, size.height),
+            },
+            None => Rect::zero(),
+        }
+    }
+
+    // Swaps left and right of the style if the direction is right-to-left. This
+    // works both ways, ie. for styles passed to and returned from taffy.
+    fn directed_style(&self, mut style: Style) -> Style {
+        if self.rtl {
+            swap_left_right(&mut style);
+        }
+        style
+    }
+
+    fn set_extras(&mut self, node: Node, extras: StyleExtras) {
+        if extras.calc.is_empty() {
+            self.calc.remove(&node);
+        } else {
+            self.calc.insert(node, extras.calc);
+        }
+        if extras.line_names.is_empty() {
+            self.line_names.remove(&node);
+        } else {
+            self.line_names.insert(node, extras.line_names);
+        }
+    }
+
+    // Returns the style of the node as passed to `node_set_style`
+    fn py_style(&self, node: Node) -> PyStyle {
+        let style = self.directed_style(self.taffy.style(node).unwrap().clone());
+        let mut style = PyStyle::from(&style);
+        if let Some(calc) = self.calc.get(&node) {
+            calc.apply(&mut style);
+        }
+        if let Some(line_names) = self.line_names.get(&node) {
+            line_names.apply(&mut style);
+        }
+        style
+    }
+
+    // Copies the style, key and children of `node` into new nodes, recursively.
+    // Measure functions are not copied.
+    fn clone_subtree(&mut self, node: Node) -> Result<Node, TaffyError> {
+        let style = self.taffy.style(node)?.clone();
+        let children = self
+            .taffy
+            .children(node)?
+            .into_iter()
+            .map(|child| self.clone_subtree(child))
+            .collect::<Result<Vec<Node>, TaffyError>>()?;
+        let clone = self.taffy.new_with_children(style, &children)?;
+        self.nodes.insert(clone);
+        if let Some(calc) = self.calc.get(&node).copied() {
+            self.calc.insert(clone, calc);
+        }
+        if let Some(line_names) = self.line_names.get(&node).cloned() {
+            self.line_names.insert(clone, line_names);
+        }
+        if let Some(key) = self.keys.get(&node).cloned() {
+            self.keys.insert(clone, key);
+        }
+        Ok(clone)
+    }
+
+    fn tree_node(&self, node: Node) -> PyTreeNode {
+        PyTreeNode {
+            style: self.py_style(node),
+            key: self.keys.get(&node).cloned(),
+            children: self
+                .taffy
+                .children(node)
+                .unwrap()
+                .into_iter()
+                .map(|child| self.tree_node(child))
+                .collect(),
+        }
+    }
+
+    fn layout_node(&self, node: Node) -> PyLayoutNode {
+        PyLayoutNode {
+            id: node_to_id(node),
+            layout: PyLayout::from(self.layout(node)),
+            key: self.keys.get(&node).cloned(),
+            children: self
+                .taffy
+                .children(node)
+                .unwrap()
+                .into_iter()
+                .map(|child| self.layout_node(child))
+                .collect(),
+        }
+    }
+
+    fn create_subtree(&mut self, style_tree: StyleTree) -> Result<Node, TaffyError> {
+        let children = style_tree
+            .children
+            .into_iter()
+            .map(|child| self.create_subtree(child))
+            .collect::<Result<Vec<Node>, TaffyError>>()?;
+        let style = self.directed_style(style_tree.style);
+        let node = self.taffy.new_with_children(style, &children)?;
+        self.nodes.insert(node);
+        self.set_extras(node, style_tree.extras);
+        if let Some(key) = style_tree.key {
+            self.keys.insert(node, key);
+        }
+        Ok(node)
+    }
+
+    // Same as `taffy::debug::print_tree`, but writes to `output` instead of
+    // stdout and shows the node ids used on the Python side (and the keys).
+    fn print_node(&self, output: &mut String, node: Node, has_sibling: bool, lines: &str) {
+        let layout = self.layout(node);
+        let style = self.taffy.style(node).unwrap();
+        let children = self.taffy.children(node).unwrap();
+        let display = match (children.len(), style.display) {
+            (_, Display::None) => "NONE",
+            (0, _) => "LEAF",
+            (_, Display::Flex) => "FLEX",
+            (_, Display::Grid) => "GRID",
+        };
+        let key = match self.keys.get(&node) {
+            Some(key) => format!(" {:?}", key),
+            None => String::new(),
+        };
+        let fork = if has_sibling {
+            "├── "
+        } else {
+            "└── "
+        };
+        _ = writeln!(
+            output,
+            "{lines}{fork} {display} [x: {x:<4} y: {y:<4} width: {width:<4} height: {height:<4}] ({node_id}){key}",
+            x = layout.location.x,
+            y = layout.location.y,
+            width = layout.size.width,
+            height = layout.size.height,
+            node_id = node_to_id(node),
+        );
+        let lines = format!("{}{}", lines, if has_sibling { "│   " } else { "    " });
+        for (index, child) in children.iter().enumerate() {
+            self.print_node(output, *child, index < children.len() - 1, &lines);
+        }
+    }
+
+    fn compute_layout(
+        &mut self,
+        py: Python,
+        node: Node,
+        available_space: Size<AvailableSpace>,
+        strict: bool,
+    ) -> PyResult<()> {
+        let start = PROFILING.load(Ordering::Relaxed).then(Instant::now);
+        MEASURE_ERROR.with(|error| *error.borrow_mut() = if strict { Some(None) } else { None });
+        MEASURE_PASS.fetch_add(1, Ordering::Relaxed);
+        self.available_spaces.insert(node, available_space);
+        self.resolve_line_names(node);
+        self.compute_taffy_layout(py, node, available_space)?;
+        for _ in 0..MAX_CALC_PASSES {
+            if !self.resolve_calc(node, available_space) {
+                break;
+            }
+            self.compute_taffy_layout(py, node, available_space)?;
+        }
+        // As with taffy, the rounded layouts are only updated when computing the
+        // layout with rounding enabled
+        if self.use_rounding {
+            self.round_layout(node, 0., 0.);
+            self.rounding_scales.insert(node, self.rounding_scale);
+        } else {
+            self.forget_rounded_layout(node);
+            self.rounding_scales.remove(&node);
+        }
+        if let Some(start) = start {
+            debug!(
+                target: "stretchable.taffylib",
+                "compute_layout node={} nodes={} duration_ms={:.3}",
+                node_to_id(node),
+                self.subtree(node).len(),
+                start.elapsed().as_secs_f64() * 1000.,
+            );
+        }
+        if let Some(err) = MEASURE_ERROR.with(|error| error.take()).flatten() {
+            // The layout is not valid, so make sure it is computed again
+            self.taffy.mark_dirty(node).unwrap();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn compute_taffy_layout(
+        &mut self,
+        py: Python,
+        node: Node,
+        available_space: Size<AvailableSpace>,
+    ) -> PyResult<()> {
+        // Release the GIL while computing the layout, unless measure functions
+        // need it to call into Python
+        let result = if self.measured.is_empty() {
+            let taffy = &mut self.taffy;
+            py.allow_threads(|| taffy.compute_layout(node, available_space))
+        } else {
+            self.taffy.compute_layout(node, available_space)
+        };
+        result.map_err(taffy_error)
+    }
+
+    // Returns the sizes of the node for each of the available spaces, by
+    // computing its layout with that available space. The layouts of the node
+    // and its descendants are restored afterwards (taffy 0.3 can only measure a
+    // node by computing its layout), so that the current layout is not changed.
+    fn measure(
+        &mut self,
+        py: Python,
+        node: Node,
+        available_spaces: &[Size<AvailableSpace>],
+    ) -> PyResult<Vec<Size<f32>>> {
+        let nodes = self.subtree(node);
+        let layouts: Vec<Layout> = nodes
+            .iter()
+            .map(|node| *self.taffy.layout(*node).unwrap())
+            .collect();
+        let dirty = self.taffy.dirty(node).unwrap();
+        let sizes = available_spaces
+            .iter()
+            .map(|available_space| {
+                // Each computation is a new pass for the cache of the measure
+                // functions, as in `compute_layout`
+                MEASURE_PASS.fetch_add(1, Ordering::Relaxed);
+                self.compute_taffy_layout(py, node, *available_space)?;
+                Ok(self.taffy.layout(node).unwrap().size)
+            })
+            .collect();
+        for (node, layout) in nodes.into_iter().zip(layouts) {
+            *self.taffy.layout_mut(node) = layout;
+        }
+        if dirty {
+            self.taffy.mark_dirty(node).unwrap();
+        }
+        sizes
+    }
+
+    // Returns the min-content and max-content widths of the node
+    fn intrinsic_widths(&mut self, py: Python, node: Node) -> PyResult<(f32, f32)> {
+        let sizes = self.measure(
+            py,
+            node,
+            &[AvailableSpace::MinContent, AvailableSpace::MaxContent].map(|width| Size {
+                width,
+                height: AvailableSpace::MaxContent,
+            }),
+        )?;
+        Ok((sizes[0].width, sizes[1].width))
+    }
+
+    // Taffy 0.3 has no calc lengths, so they are computed as the percentage
+    // first and then resolved against the size of the parent from the computed
+    // layout, after which the layout is computed again. Returns true if any of
+    // the styles changed, ie. if the layout must be computed again.
+    //
+    // The parent size is the size of its content box, which is what taffy
+    // resolves percentages against for flexbox children, but not for children
+    // of a grid (which use the grid area) or absolutely positioned children
+    // (which use the padding box). Nodes that affect the size of their parent
+    // may not converge, in which case the layout of the last pass is used.
+    fn resolve_calc(&mut self, root: Node, available_space: Size<AvailableSpace>) -> bool {
+        if self.calc.is_empty() {
+            return false;
+        }
+        let root_size = available_space.into_options();
+        let mut changed = false;
+        for node in self.subtree(root) {
+            let Some(calc) = self.calc.get(&node).copied() else {
+                continue;
+            };
+            let parent_size = match self.taffy.parent(node) {
+                Some(parent) if node != root => self
+                    .content_size(parent, root_size.width.unwrap_or(0.))
+                    .map(Some),
+                _ => root_size,
+            };
+            let mut style = self.taffy.style(node).unwrap().clone();
+            calc.resolve(&mut style, parent_size);
+            if style != *self.taffy.style(node).unwrap() {
+                self.taffy.set_style(node, style).unwrap();
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // Taffy 0.3 has no named grid lines, so grid items placed at a named line
+    // are placed at the line with that name in the parent before the layout is
+    // computed (or auto, if the parent has no line with that name).
+    fn resolve_line_names(&mut self, root: Node) {
+        if self.line_names.is_empty() {
+            return;
+        }
+        for node in self.subtree(root) {
+            let Some(line_names) = self.line_names.get(&node) else {
+                continue;
+            };
+            if !line_names.is_placed() {
+                continue;
+            }
+            let parent = self.taffy.parent(node);
+            let parent_names = parent.and_then(|parent| self.line_names.get(&parent));
+            let mut style = self.taffy.style(node).unwrap().clone();
+            line_names.resolve(&mut style, parent_names);
+            if style != *self.taffy.style(node).unwrap() {
+                self.taffy.set_style(node, style).unwrap();
+            }
+        }
+    }
+
+    // Returns the size of the content box of the node from the unrounded layout.
+    // Percentages of the padding and border are resolved against the content
+    // width of the parent, or `root_width` for a node without a parent.
+    fn content_size(&self, node: Node, root_width: f32) -> Size<f32> {
+        let parent_width = match self.taffy.parent(node) {
+            Some(parent) => self.content_size(parent, root_width).width,
+            None => root_width,
+        };
+        let resolve = |length| match length {
+            LengthPercentage::Points(value) => value,
+            LengthPercentage::Percent(value) => value * parent_width,
+        };
+        let style = self.taffy.style(node).unwrap();
+        let edges = |rect: Rect<LengthPercentage>| Size {
+            width: resolve(rect.left) + resolve(rect.right),
+            height: resolve(rect.top) + resolve(rect.bottom),
+        };
+        let (padding, border) = (edges(style.padding), edges(style.border));
+        let size = self.taffy.layout(node).unwrap().size;
+        Size {
+            width: (size.width - padding.width - border.width).max(0.),
+            height: (size.height - padding.height - border.height).max(0.),
+        }
+    }
+
+    // Returns the width of the content box of the parent of the node, which is
+    // what taffy resolves percentages of the margin, border and padding against,
+    // or `None` for a node without a parent.
+    fn parent_content_width(&self, node: Node) -> Option<f32> {
+        let parent = self.taffy.parent(node)?;
+        let mut root = parent;
+        while let Some(next) = self.taffy.parent(root) {
+            root = next;
+        }
+        let root_width = self
+            .available_spaces
+            .get(&root)
+            .and_then(|available_space| available_space.width.into_option())
+            .unwrap_or(0.);
+        Some(self.content_size(parent, root_width).width)
+    }
+
+    fn forget_rounded_layout(&mut self, node: Node) {
+        self.rounded_layouts.remove(&node);
+        for child in self.taffy.children(node).unwrap() {
+            self.forget_rounded_layout(child);
+        }
+    }
+
+    // Returns the node and all its descendants, depth-first (parents before
+    // their children)
+    fn subtree(&self, root: Node) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(self.taffy.children(node).unwrap().into_iter().rev());
+        }
+        nodes
+    }
+
+    // Returns the children of the node in the order they are painted (back to
+    // front): by their `order` in the layout, with absolutely positioned children
+    // after the others (as in CSS). Taffy 0.3 sets `order` to the index of the
+    // child for flexbox, so this is needed for absolutely positioned overlays to
+    // be on top. Used for both painting and hit testing, so they agree.
+    fn paint_order(&self, node: Node) -> Vec<Node> {
+        let mut children = self.taffy.children(node).unwrap();
+        children.sort_by_key(|child| {
+            let absolute = self.taffy.style(*child).unwrap().position == Position::Absolute;
+            (absolute, self.layout(*child).order)
+        });
+        children
+    }
+
+    // Moves the node (and its subtree) to `index` among the children of
+    // `new_parent`, removing it from its current parent first, if any. The index
+    // is among the other children, ie. without the node itself.
+    fn move_node(&mut self, node: Node, new_parent: Node, index: usize) -> PyResult<()> {
+        // Moving a node into its own subtree would create a cycle
+        let mut ancestor = Some(new_parent);
+        while let Some(n) = ancestor {
+            if n == node {
+                return Err(PyValueError::new_err(format!(
+                    "node {} cannot be moved into its own subtree",
+                    node_to_id(node)
+                )));
+            }
+            ancestor = self.taffy.parent(n);
+        }
+        let mut children = self.taffy.children(new_parent).map_err(taffy_error)?;
+        children.retain(|child| *child != node);
+        if index > children.len() {
+            return Err(index_error(TaffyError::ChildIndexOutOfBounds {
+                parent: new_parent,
+                child_index: index,
+                child_count: children.len(),
+            }));
+        }
+        if let Some(parent) = self.taffy.parent(node) {
+            if parent != new_parent {
+                self.taffy.remove_child(parent, node).map_err(taffy_error)?;
+            }
+        }
+        children.insert(index, node);
+        self.taffy
+            .set_children(new_parent, &children)
+            .map_err(taffy_error)
+    }
+
+    // See `node_dirty_recursive`
+    fn dirty_recursive(&self, root: Node) -> bool {
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if self.taffy.dirty(node).unwrap() {
+                return true;
+            }
+            if self.taffy.style(node).unwrap().display != Display::None {
+                stack.extend(self.taffy.children(node).unwrap());
+            }
+        }
+        false
+    }
+
+    fn drop_node(&mut self, node: Node) {
+        // Taffy does not drop the measure function when removing the node, so
+        // remove it first to release the Python objects it references
+        if self.measured.remove(&node) {
+            self.taffy.set_measure(node, None).unwrap();
+        }
+        _ = self.taffy.remove(node);
+        self.nodes.remove(&node);
+        self.rounded_layouts.remove(&node);
+        self.calc.remove(&node);
+        self.line_names.remove(&node);
+        self.available_spaces.remove(&node);
+        self.rounding_scales.remove(&node);
+        self.scroll_offsets.remove(&node);
+        self.scroll_paddings.remove(&node);
+        self.keys.remove(&node);
+    }
+}
+
+// Trees are owned by this registry and referenced from Python by an opaque
+// handle, so that a stale or repeated handle can never be dereferenced. Each
+// tree has its own lock, since taffy does not support concurrent changes: calls
+// that change the tree (including computing the layout) take the write lock,
+// while calls that only read from the tree take the read lock, so they can run
+// concurrently.
+static TREES: OnceLock<Mutex<HashMap<u64, Arc<RwLock<Tree>>>>> = OnceLock::new();
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    // The handles of the trees the current thread has locked
+    static LOCKED: RefCell<HashSet<u64>> = RefCell::new(HashSet::new());
+}
+
+// The registry is only locked while looking up, inserting or removing a tree
+// (and never while calling into Python), so it can be locked directly.
+fn trees() -> MutexGuard<'static, HashMap<u64, Arc<RwLock<Tree>>>> {
+    TREES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+fn get_tree(taffy_ptr: u64) -> PyResult<Arc<RwLock<Tree>>> {
+    trees().get(&taffy_ptr).cloned().ok_or_else(|| {
+        PyKeyError::new_err(format!(
+            "taffy handle {} is not valid (it may have been freed)",
+            taffy_ptr
+        ))
+    })
+}
+
+struct TreeGuard<G> {
+    taffy_ptr: u64,
+    guard: G,
+}
+
+impl<G> Drop for TreeGuard<G> {
+    fn drop(&mut self) {
+        LOCKED.with(|locked| locked.borrow_mut().remove(&self.taffy_ptr));
+    }
+}
+
+// Locks the tree with `try_lock`, or with `lock` if another thread has it locked.
+fn lock_tree<G>(
+    taffy_ptr: u64,
+    try_lock: impl Fn() -> TryLockResult<G>,
+    lock: impl Fn() + Sync,
+) -> PyResult<TreeGuard<G>> {
+    // If this thread has the tree locked already, we are being called
+    // re-entrantly, eg. from a `measure` function during `node_compute_layout`.
+    if LOCKED.with(|locked| locked.borrow().contains(&taffy_ptr)) {
+        return Err(PyRuntimeError::new_err(
+            "taffy is busy and cannot be accessed (eg. from within a `measure` function)",
+        ));
+    }
+    let guard = loop {
+        match try_lock() {
+            Ok(guard) => break guard,
+            Err(TryLockError::Poisoned(err)) => break err.into_inner(),
+            // Another thread has the tree locked (eg. during a layout
+            // computation with the GIL released), so wait for it without
+            // holding the GIL, since the other thread may need the GIL to finish.
+            Err(TryLockError::WouldBlock) => Python::with_gil(|py| py.allow_threads(&lock)),
+        }
+    };
+    LOCKED.with(|locked| locked.borrow_mut().insert(taffy_ptr));
+    Ok(TreeGuard { taffy_ptr, guard })
+}
+
+// Panics must not unwind across the FFI boundary, so they are converted into a
+// `RuntimeError` carrying the panic message.
+fn catch_panic<T>(f: impl FnOnce() -> PyResult<T>) -> PyResult<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "unknown error".to_string()
+            };
+            Err(PyRuntimeError::new_err(message))
+        }
+    }
+}
+
+// Calls `f` with the tree locked for writing
+fn with_tree<T>(taffy_ptr: u64, f: impl FnOnce(&mut Tree) -> PyResult<T>) -> PyResult<T> {
+    let tree = get_tree(taffy_ptr)?;
+    let mut guard = lock_tree(taffy_ptr, || tree.try_write(), || drop(tree.write()))?;
+    catch_panic(|| f(&mut guard.guard))
+}
+
+// Calls `f` with the tree locked for reading
+fn with_tree_ref<T>(taffy_ptr: u64, f: impl FnOnce(&Tree) -> PyResult<T>) -> PyResult<T> {
+    let tree = get_tree(taffy_ptr)?;
+    let guard = lock_tree(taffy_ptr, || tree.try_read(), || drop(tree.read()))?;
+    catch_panic(|| f(&guard.guard))
+}
+
+fn taffy_error(err: TaffyError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+// Same as `taffy_error`, except that an out of bounds child index raises `IndexError`
+fn index_error(err: TaffyError) -> PyErr {
+    match err {
+        TaffyError::ChildIndexOutOfBounds { .. } => PyIndexError::new_err(err.to_string()),
+        _ => taffy_error(err),
+    }
+}
+
+fn node_from_id(node_id: u64) -> Node {
+    Node::from(KeyData::from_ffi(node_id))
+}
+
+fn node_to_id(node: Node) -> u64 {
+    // The version of an occupied slot is always odd, so a node id is never 0
+    // and 0 can safely be used as a sentinel on the Python side.
+    let node_id = node.data().as_ffi();
+    debug_assert_ne!(node_id, 0);
+    node_id
+}
+
+// Returns the node corresponding to `node_id`, or raises `KeyError` if the id
+// does not refer to a node in the tree.
+fn lookup(tree: &Tree, node_id: u64) -> PyResult<Node> {
+    let node = node_from_id(node_id);
+    if node_to_id(node) == node_id && tree.nodes.contains(&node) {
+        Ok(node)
+    } else {
+        Err(PyKeyError::new_err(node_id))
+    }
+}
+
+#[pyfunction]
+fn init() -> PyResult<u64> {
+    let taffy_ptr = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    trees().insert(taffy_ptr, Arc::new(RwLock::new(Tree::new())));
+    Ok(taffy_ptr)
+}
+
+// Drops the tree, returning whether there was a tree to drop. This is safe to
+// call with any handle, including handles that were already freed or never
+// issued, and while the tree is in use (eg. from a measure function during
+// `node_compute_layout`), in which case the tree is dropped once no longer used.
+#[pyfunction]
+fn free(taffy_ptr: u64) -> PyResult<bool> {
+    // Take the tree out of the registry before dropping it, since dropping
+    // may release Python objects held by measure functions.
+    let tree = trees().remove(&taffy_ptr);
+    Ok(tree.is_some())
+}
+
+fn round_to_scale(value: f32, scale: f32) -> f32 {
+    (value * scale).round() / scale
+}
+
+// Same as the rounding in `taffy::compute::compute_layout`: positions are
+// rounded based on the cumulative coordinates to avoid introducing gaps. The
+// cumulative coordinates are the (unrounded) absolute location of the parent.
+fn rounded_layout(unrounded: Layout, cumulative_x: f32, cumulative_y: f32, scale: f32) -> Layout {
+    let round = |value| round_to_scale(value, scale);
+    let cumulative_x = cumulative_x + unrounded.location.x;
+    let cumulative_y = cumulative_y + unrounded.location.y;
+    let mut layout = unrounded;
+    layout.location.x = round(unrounded.location.x);
+    layout.location.y = round(unrounded.location.y);
+    layout.size.width = round(cumulative_x + unrounded.size.width) - round(cumulative_x);
+    layout.size.height = round(cumulative_y + unrounded.size.height) - round(cumulative_y);
+    layout
+}
+
+// Rounds an unrounded layout (eg. from `node_get_unrounded_layout`) the same way
+// as when rounding is enabled, without changing any tree. `parent_x` and
+// `parent_y` are the unrounded absolute location of the parent, which is needed
+// to round the size the same way as taffy.
+#[pyfunction]
+#[pyo3(signature = (layout, scale=1., parent_x=0., parent_y=0.))]
+fn round_layout(layout: PyLayout, scale: f32, parent_x: f32, parent_y: f32) -> PyResult<PyLayout> {
+    if !(scale.is_finite() && scale > 0.) {
+        return Err(PyValueError::new_err(format!(
+            "rounding scale must be a positive number, got {}",
+            scale
+        )));
+    }
+    let unrounded = Layout {
+        order: layout.order as u32,
+        size: Size {
+            width: layout.width,
+            height: layout.height,
+        },
+        location: Point {
+            x: layout.left,
+            y: layout.top,
+        },
+    };
+    Ok(PyLayout::from(rounded_layout(
+        unrounded, parent_x, parent_y, scale,
+    )))
+}
+
+#[pyfunction]
+fn enable_rounding(taffy_ptr: u64) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        tree.use_rounding = true;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn disable_rounding(taffy_ptr: u64) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        tree.use_rounding = false;
+        Ok(())
+    })
+}
+
+// Whether the duration of each `node_compute_layout` is logged, see
+// `set_profiling`
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+// Resets the levels of the Python loggers cached by `pyo3_log`
+static LOG_RESET: OnceLock<ResetHandle> = OnceLock::new();
+
+// Enables or disables logging the duration and number of nodes of each layout
+// computation (at debug level, to the `stretchable.taffylib` logger). This is
+// off by default. The levels of the Python loggers are cached, so they are
+// reset here, ie. the level of the logger should be set before enabling this.
+#[pyfunction]
+fn set_profiling(enabled: bool) -> PyResult<()> {
+    PROFILING.store(enabled, Ordering::Relaxed);
+    if let Some(reset) = LOG_RESET.get() {
+        reset.reset();
+    }
+    Ok(())
+}
+
+#[pyfunction]
+fn is_rounding_enabled(taffy_ptr: u64) -> PyResult<bool> {
+    with_tree_ref(taffy_ptr, |tree| Ok(tree.use_rounding))
+}
+
+// Sets the direction of the tree to right-to-left (`rtl`) or left-to-right.
+//
+// Taffy has no notion of direction, so right-to-left is approximated: left and
+// right of `inset`, `margin`, `border` and `padding` are swapped when styles
+// are passed to taffy, and the computed locations are mirrored within the
+// parent when layouts are returned. This mirrors the order of items in flex
+// rows and grid columns, but eg. `flex_direction`, alignment and measure
+// functions are not aware of the direction, and the result is not always the
+// same as a browser would produce.
+#[pyfunction]
+fn set_direction(taffy_ptr: u64, rtl: bool) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        if tree.rtl == rtl {
+            return Ok(());
+        }
+        // Swap left and right of the existing styles
+        for node in tree.nodes.iter() {
+            let mut style = tree.taffy.style(*node).unwrap().clone();
+            swap_left_right(&mut style);
+            tree.taffy.set_style(*node, style).unwrap();
+        }
+        tree.rtl = rtl;
+        Ok(())
+    })
+}
+
+// Sets the scale used when rounding is enabled, so that layouts are rounded to
+// multiples of `1 / scale` (eg. to half pixels with a scale of 2 for HiDPI
+// displays), rather than to integers. As with `enable_rounding` and
+// `disable_rounding`, this takes effect the next time the layout is computed.
 #[pyfunction]
-fn init() -> usize {
-    let taffy = Taffy::new();
-    Box::into_raw(Box::new(taffy)) as usize
+fn set_rounding_scale(taffy_ptr: u64, scale: f32) -> PyResult<()> {
+    if !(scale.is_finite() && scale > 0.) {
+        return Err(PyValueError::new_err(format!(
+            "rounding scale must be a positive number, got {}",
+            scale
+        )));
+    }
+    with_tree(taffy_ptr, |tree| {
+        tree.rounding_scale = scale;
+        Ok(())
+    })
 }
 
 #[pyfunction]
-fn free(taffy_ptr: usize) {
-    let _ = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
+fn total_node_count(taffy_ptr: u64) -> PyResult<usize> {
+    with_tree_ref(taffy_ptr, |tree| Ok(tree.taffy.total_node_count()))
 }
 
+// Returns the nodes without a parent, ordered by slot so the result is stable
+// between calls.
 #[pyfunction]
-fn enable_rounding(taffy_ptr: usize) {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    taffy.enable_rounding();
-    Box::leak(taffy);
+fn tree_roots(taffy_ptr: u64) -> PyResult<Vec<u64>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let mut roots: Vec<Node> = tree
+            .nodes
+            .iter()
+            .copied()
+            .filter(|node| tree.taffy.parent(*node).is_none())
+            .collect();
+        roots.sort();
+        Ok(roots.into_iter().map(node_to_id).collect())
+    })
 }
 
+// Reserves capacity for at least `additional` more nodes. Taffy 0.3 can only
+// reserve capacity when it is created, so this reserves the storage kept here
+// for each node, not the storage of taffy itself.
 #[pyfunction]
-fn disable_rounding(taffy_ptr: usize) {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    taffy.disable_rounding();
-    Box::leak(taffy);
+fn reserve(taffy_ptr: u64, additional: usize) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        tree.nodes.reserve(additional);
+        tree.rounded_layouts.reserve(additional);
+        Ok(())
+    })
 }
 
 // STYLE
 
-trait FromIndex<T> {
-    fn from_index(index: i32) -> T;
+// An index (or the `dim` of a length) which does not correspond to any variant of
+// the enum it is converted to. See `invalid_field` for the error raised in Python.
+pub struct InvalidIndex(i32);
+
+// Taffy's enums are foreign types, so `TryFrom<i32>` cannot be implemented for
+// them here
+trait TryFromIndex: Sized {
+    fn try_from_index(index: i32) -> Result<Self, InvalidIndex>;
+}
+
+fn invalid_field(field: &str, InvalidIndex(index): InvalidIndex) -> PyErr {
+    PyValueError::new_err(format!("invalid index {} for {}", index, field))
+}
+
+// Converts the index of a style field, raising `ValueError` naming the field if
+// it is invalid
+fn field_from_index<T: TryFromIndex>(field: &str, index: i32) -> PyResult<T> {
+    T::try_from_index(index).map_err(|err| invalid_field(field, err))
+}
+
+fn optional_field_from_index<T: TryFromIndex>(
+    field: &str,
+    index: Option<i32>,
+) -> PyResult<Option<T>> {
+    index
+        .map(|index| field_from_index(field, index))
+        .transpose()
 }
 
-trait FromIndexOptional<T> {
-    fn from_index(index: Option<i32>) -> Option<T>;
+trait ToIndex {
+    fn to_index(self) -> i32;
 }
 
-impl FromIndex<Display> for Display {
-    fn from_index(index: i32) -> Display {
-        match index {
+impl TryFromIndex for Display {
+    fn try_from_index(index: i32) -> Result<Display, InvalidIndex> {
+        Ok(match index {
             0 => Display::None,
             1 => Display::Flex,
             2 => Display::Grid,
-            _ => panic!("invalid index {}", index),
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for Display {
+    fn to_index(self) -> i32 {
+        match self {
+            Display::None => 0,
+            Display::Flex => 1,
+            Display::Grid => 2,
         }
     }
 }
 
-impl FromIndex<Position> for Position {
-    fn from_index(index: i32) -> Position {
-        match index {
+impl TryFromIndex for Position {
+    fn try_from_index(index: i32) -> Result<Position, InvalidIndex> {
+        Ok(match index {
             0 => Position::Relative,
             1 => Position::Absolute,
-            _ => panic!("invalid index {}", index),
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for Position {
+    fn to_index(self) -> i32 {
+        match self {
+            Position::Relative => 0,
+            Position::Absolute => 1,
         }
     }
 }
 
-impl FromIndex<FlexWrap> for FlexWrap {
-    fn from_index(index: i32) -> FlexWrap {
-        match index {
+impl TryFromIndex for FlexWrap {
+    fn try_from_index(index: i32) -> Result<FlexWrap, InvalidIndex> {
+        Ok(match index {
             0 => FlexWrap::NoWrap,
             1 => FlexWrap::Wrap,
             2 => FlexWrap::WrapReverse,
-            _ => panic!("invalid index {}", index),
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for FlexWrap {
+    fn to_index(self) -> i32 {
+        match self {
+            FlexWrap::NoWrap => 0,
+            FlexWrap::Wrap => 1,
+            FlexWrap::WrapReverse => 2,
         }
     }
 }
 
-impl FromIndex<FlexDirection> for FlexDirection {
-    fn from_index(index: i32) -> FlexDirection {
-        match index {
+impl TryFromIndex for FlexDirection {
+    fn try_from_index(index: i32) -> Result<FlexDirection, InvalidIndex> {
+        Ok(match index {
             0 => FlexDirection::Row,
             1 => FlexDirection::Column,
             2 => FlexDirection::RowReverse,
             3 => FlexDirection::ColumnReverse,
-            _ => panic!("invalid index {}", index),
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for FlexDirection {
+    fn to_index(self) -> i32 {
+        match self {
+            FlexDirection::Row => 0,
+            FlexDirection::Column => 1,
+            FlexDirection::RowReverse => 2,
+            FlexDirection::ColumnReverse => 3,
         }
     }
 }
 
 // AlignItems, JustifyItems, AlignSelf, JustifySelf
-impl FromIndexOptional<AlignItems> for AlignItems {
-    fn from_index(index: Option<i32>) -> Option<AlignItems> {
-        match index {
-            None => None,
-            Some(n) => match n {
-                0 => Some(AlignItems::Start),
-                1 => Some(AlignItems::End),
-                2 => Some(AlignItems::FlexStart),
-                3 => Some(AlignItems::FlexEnd),
-                4 => Some(AlignItems::Center),
-                5 => Some(AlignItems::Baseline),
-                6 => Some(AlignItems::Stretch),
-                _ => panic!("invalid index {}", n),
-            },
+impl TryFromIndex for AlignItems {
+    fn try_from_index(index: i32) -> Result<AlignItems, InvalidIndex> {
+        Ok(match index {
+            0 => AlignItems::Start,
+            1 => AlignItems::End,
+            2 => AlignItems::FlexStart,
+            3 => AlignItems::FlexEnd,
+            4 => AlignItems::Center,
+            5 => AlignItems::Baseline,
+            6 => AlignItems::Stretch,
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for AlignItems {
+    fn to_index(self) -> i32 {
+        match self {
+            AlignItems::Start => 0,
+            AlignItems::End => 1,
+            AlignItems::FlexStart => 2,
+            AlignItems::FlexEnd => 3,
+            AlignItems::Center => 4,
+            AlignItems::Baseline => 5,
+            AlignItems::Stretch => 6,
         }
     }
 }
 
 // AlignContent, JustifyContent
-impl FromIndexOptional<AlignContent> for AlignContent {
-    fn from_index(index: Option<i32>) -> Option<AlignContent> {
-        match index {
-            None => None,
-            Some(n) => match n {
-                0 => Some(AlignContent::Start),
-                1 => Some(AlignContent::End),
-                2 => Some(AlignContent::FlexStart),
-                3 => Some(AlignContent::FlexEnd),
-                4 => Some(AlignContent::Center),
-                5 => Some(AlignContent::Stretch),
-                6 => Some(AlignContent::SpaceBetween),
-                7 => Some(AlignContent::SpaceEvenly),
-                8 => Some(AlignContent::SpaceAround),
-                _ => panic!("invalid index {}", n),
-            },
+impl TryFromIndex for AlignContent {
+    fn try_from_index(index: i32) -> Result<AlignContent, InvalidIndex> {
+        Ok(match index {
+            0 => AlignContent::Start,
+            1 => AlignContent::End,
+            2 => AlignContent::FlexStart,
+            3 => AlignContent::FlexEnd,
+            4 => AlignContent::Center,
+            5 => AlignContent::Stretch,
+            6 => AlignContent::SpaceBetween,
+            7 => AlignContent::SpaceEvenly,
+            8 => AlignContent::SpaceAround,
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for AlignContent {
+    fn to_index(self) -> i32 {
+        match self {
+            AlignContent::Start => 0,
+            AlignContent::End => 1,
+            AlignContent::FlexStart => 2,
+            AlignContent::FlexEnd => 3,
+            AlignContent::Center => 4,
+            AlignContent::Stretch => 5,
+            AlignContent::SpaceBetween => 6,
+            AlignContent::SpaceEvenly => 7,
+            AlignContent::SpaceAround => 8,
         }
     }
 }
 
-impl FromIndex<GridAutoFlow> for GridAutoFlow {
-    fn from_index(index: i32) -> GridAutoFlow {
-        match index {
+impl TryFromIndex for GridAutoFlow {
+    fn try_from_index(index: i32) -> Result<GridAutoFlow, InvalidIndex> {
+        Ok(match index {
             0 => GridAutoFlow::Row,
             1 => GridAutoFlow::Column,
             2 => GridAutoFlow::RowDense,
             3 => GridAutoFlow::ColumnDense,
-            _ => panic!("invalid index {}", index),
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
+
+impl ToIndex for GridAutoFlow {
+    fn to_index(self) -> i32 {
+        match self {
+            GridAutoFlow::Row => 0,
+            GridAutoFlow::Column => 1,
+            GridAutoFlow::RowDense => 2,
+            GridAutoFlow::ColumnDense => 3,
         }
     }
 }
 
-#[derive(FromPyObject, IntoPyObject)]
+// `offset` is only used by calc lengths (dim 8), where `value` is the percentage,
+// and is left out of the dict otherwise
+#[derive(FromPyObject)]
 struct PyLength {
     dim: i32,
     value: f32,
+    offset: Option<f32>,
 }
 
-impl Into<PyLength> for AvailableSpace {
-    fn into(self: AvailableSpace) -> PyLength {
-        match self {
+impl IntoPy<PyObject> for PyLength {
+    fn into_py(self, py: Python) -> PyObject {
+        let dict = PyDict::new(py);
+        dict.set_item("dim", self.dim).unwrap();
+        dict.set_item("value", self.value).unwrap();
+        if let Some(offset) = self.offset {
+            dict.set_item("offset", offset).unwrap();
+        }
+        dict.into()
+    }
+}
+
+impl From<AvailableSpace> for PyLength {
+    fn from(available_space: AvailableSpace) -> PyLength {
+        match available_space {
             AvailableSpace::Definite(value) => PyLength {
                 dim: 1,
-                value: value,
+                value,
+                offset: None,
+            },
+            AvailableSpace::MinContent => PyLength {
+                dim: 3,
+                value: 0.,
+                offset: None,
+            },
+            AvailableSpace::MaxContent => PyLength {
+                dim: 4,
+                value: 0.,
+                offset: None,
             },
-            AvailableSpace::MinContent => PyLength { dim: 3, value: 0. },
-            AvailableSpace::MaxContent => PyLength { dim: 4, value: 0. },
         }
     }
 }
 
-impl From<PyLength> for Dimension {
-    fn from(length: PyLength) -> Dimension {
-        match length.dim {
-            0 => Dimension::Auto,
-            1 => Dimension::Points(length.value),
-            2 => Dimension::Percent(length.value),
-            _ => panic!("unsupported dimension {}", length.dim),
+// Dimensions without a value use NAN for `value`, same as the Python side
+impl From<Dimension> for PyLength {
+    fn from(dimension: Dimension) -> PyLength {
+        match dimension {
+            Dimension::Auto => PyLength {
+                dim: 0,
+                value: f32::NAN,
+                offset: None,
+            },
+            Dimension::Points(value) => PyLength {
+                dim: 1,
+                value,
+                offset: None,
+            },
+            Dimension::Percent(value) => PyLength {
+                dim: 2,
+                value,
+                offset: None,
+            },
         }
     }
 }
 
-impl From<PyLength> for AvailableSpace {
-    fn from(length: PyLength) -> Self {
-        match length.dim {
-            1 => AvailableSpace::Definite(length.value),
-            3 => AvailableSpace::MinContent,
-            4 => AvailableSpace::MaxContent,
-            _ => panic!("unsupported dimension {}", length.dim),
+impl From<LengthPercentageAuto> for PyLength {
+    fn from(length: LengthPercentageAuto) -> PyLength {
+        match length {
+            LengthPercentageAuto::Auto => PyLength {
+                dim: 0,
+                value: f32::NAN,
+                offset: None,
+            },
+            LengthPercentageAuto::Points(value) => PyLength {
+                dim: 1,
+                value,
+                offset: None,
+            },
+            LengthPercentageAuto::Percent(value) => PyLength {
+                dim: 2,
+                value,
+                offset: None,
+            },
         }
     }
 }
 
-impl From<PyLength> for LengthPercentageAuto {
-    fn from(length: PyLength) -> LengthPercentageAuto {
-        match length.dim {
-            0 => LengthPercentageAuto::Auto,
-            1 => LengthPercentageAuto::Points(length.value),
-            2 => LengthPercentageAuto::Percent(length.value),
-            _ => panic!("unsupported dimension {}", length.dim),
+impl From<LengthPercentage> for PyLength {
+    fn from(length: LengthPercentage) -> PyLength {
+        match length {
+            LengthPercentage::Points(value) => PyLength {
+                dim: 1,
+                value,
+                offset: None,
+            },
+            LengthPercentage::Percent(value) => PyLength {
+                dim: 2,
+                value,
+                offset: None,
+            },
         }
     }
 }
 
-impl From<PyLength> for LengthPercentage {
-    fn from(length: PyLength) -> LengthPercentage {
-        match length.dim {
+impl TryFrom<PyLength> for Dimension {
+    type Error = InvalidIndex;
+
+    fn try_from(length: PyLength) -> Result<Dimension, InvalidIndex> {
+        Ok(match length.dim {
+            0 => Dimension::Auto,
+            1 => Dimension::Points(length.value),
+            2 => Dimension::Percent(length.value),
+            // Calc lengths start out as the percentage and are resolved after the
+            // layout is computed, see `Tree::resolve_calc`
+            8 => Dimension::Percent(length.value),
+            _ => return Err(InvalidIndex(length.dim)),
+        })
+    }
+}
+
+impl TryFrom<PyLength> for AvailableSpace {
+    type Error = InvalidIndex;
+
+    fn try_from(length: PyLength) -> Result<AvailableSpace, InvalidIndex> {
+        Ok(match length.dim {
+            1 => AvailableSpace::Definite(length.value),
+            3 => AvailableSpace::MinContent,
+            4 => AvailableSpace::MaxContent,
+            _ => return Err(InvalidIndex(length.dim)),
+        })
+    }
+}
+
+impl TryFrom<PyLength> for LengthPercentageAuto {
+    type Error = InvalidIndex;
+
+    fn try_from(length: PyLength) -> Result<LengthPercentageAuto, InvalidIndex> {
+        Ok(match length.dim {
+            0 => LengthPercentageAuto::Auto,
+            1 => LengthPercentageAuto::Points(length.value),
+            2 => LengthPercentageAuto::Percent(length.value),
+            _ => return Err(InvalidIndex(length.dim)),
+        })
+    }
+}
+
+impl TryFrom<PyLength> for LengthPercentage {
+    type Error = InvalidIndex;
+
+    fn try_from(length: PyLength) -> Result<LengthPercentage, InvalidIndex> {
+        Ok(match length.dim {
             1 => LengthPercentage::Points(length.value),
             2 => LengthPercentage::Percent(length.value),
-            _ => panic!("unsupported dimension {}", length.dim),
-        }
+            _ => return Err(InvalidIndex(length.dim)),
+        })
     }
 }
 
+// Converts a length of a style field (or of an argument), raising `ValueError`
+// naming the field if its dimension is invalid
+fn length_field<T: TryFrom<PyLength, Error = InvalidIndex>>(
+    field: &str,
+    length: PyLength,
+) -> PyResult<T> {
+    T::try_from(length).map_err(|err| invalid_field(field, err))
+}
+
 #[derive(FromPyObject, IntoPyObject)]
 pub struct PySize {
     width: PyLength,
     height: PyLength,
 }
 
-impl From<PySize> for Size<Dimension> {
-    fn from(size: PySize) -> Self {
-        Size {
-            height: Dimension::from(size.height),
-            width: Dimension::from(size.width),
+// Same as `length_field`, with the fields of the lengths named eg. `size.width`
+fn size_field<T: TryFrom<PyLength, Error = InvalidIndex>>(
+    field: &str,
+    size: PySize,
+) -> PyResult<Size<T>> {
+    Ok(Size {
+        width: length_field(&format!("{}.width", field), size.width)?,
+        height: length_field(&format!("{}.height", field), size.height)?,
+    })
+}
+
+impl<T: Into<PyLength>> From<Size<T>> for PySize {
+    fn from(size: Size<T>) -> Self {
+        PySize {
+            width: size.width.into(),
+            height: size.height.into(),
+        }
+    }
+}
+
+#[derive(FromPyObject, IntoPyObject)]
+pub struct PyRect {
+    left: PyLength,
+    right: PyLength,
+    top: PyLength,
+    bottom: PyLength,
+}
+
+// Same as `length_field`, with the fields of the lengths named eg. `margin.left`
+fn rect_field<T: TryFrom<PyLength, Error = InvalidIndex>>(
+    field: &str,
+    rect: PyRect,
+) -> PyResult<Rect<T>> {
+    Ok(Rect {
+        left: length_field(&format!("{}.left", field), rect.left)?,
+        right: length_field(&format!("{}.right", field), rect.right)?,
+        top: length_field(&format!("{}.top", field), rect.top)?,
+        bottom: length_field(&format!("{}.bottom", field), rect.bottom)?,
+    })
+}
+
+impl<T: Into<PyLength>> From<Rect<T>> for PyRect {
+    fn from(rect: Rect<T>) -> Self {
+        PyRect {
+            left: rect.left.into(),
+            right: rect.right.into(),
+            top: rect.top.into(),
+            bottom: rect.bottom.into(),
+        }
+    }
+}
+
+// `name` is only used for lines placed by name (kind 1), which are resolved when
+// the layout is computed (see `Tree::resolve_line_names`), and is left out of
+// the dict otherwise
+#[derive(FromPyObject)]
+pub struct PyGridIndex {
+    kind: i8,
+    value: i16,
+    name: Option<String>,
+}
+
+impl IntoPy<PyObject> for PyGridIndex {
+    fn into_py(self, py: Python) -> PyObject {
+        let dict = PyDict::new(py);
+        dict.set_item("kind", self.kind).unwrap();
+        dict.set_item("value", self.value).unwrap();
+        if let Some(name) = self.name {
+            dict.set_item("name", name).unwrap();
+        }
+        dict.into()
+    }
+}
+
+impl TryFrom<PyGridIndex> for GridPlacement {
+    type Error = InvalidIndex;
+
+    fn try_from(grid_index: PyGridIndex) -> Result<GridPlacement, InvalidIndex> {
+        Ok(match grid_index.kind {
+            1 if grid_index.name.is_some() => Self::Auto,
+            1 => Self::from_line_index(grid_index.value),
+            2 if grid_index.value < 0 => return Err(InvalidIndex(grid_index.value.into())),
+            2 => Self::from_span(grid_index.value as u16),
+            _ => Self::Auto,
+        })
+    }
+}
+
+impl From<GridPlacement> for PyGridIndex {
+    fn from(placement: GridPlacement) -> Self {
+        match placement {
+            GridPlacement::Auto => PyGridIndex {
+                kind: 0,
+                value: 0,
+                name: None,
+            },
+            GridPlacement::Line(line) => PyGridIndex {
+                kind: 1,
+                value: line.as_i16(),
+                name: None,
+            },
+            GridPlacement::Span(span) => PyGridIndex {
+                kind: 2,
+                value: span as i16,
+                name: None,
+            },
+        }
+    }
+}
+
+#[derive(FromPyObject, IntoPyObject)]
+pub struct PyGridPlacement {
+    start: PyGridIndex,
+    end: PyGridIndex,
+}
+
+// Converts the placement of a style field, raising `ValueError` naming the line
+// (eg. `grid_row.start`) if it is invalid
+fn placement_field(field: &str, grid_placement: PyGridPlacement) -> PyResult<Line<GridPlacement>> {
+    let line = |name, grid_index| {
+        GridPlacement::try_from(grid_index)
+            .map_err(|err| invalid_field(&format!("{}.{}", field, name), err))
+    };
+    Ok(Line {
+        start: line("start", grid_placement.start)?,
+        end: line("end", grid_placement.end)?,
+    })
+}
+
+impl From<Line<GridPlacement>> for PyGridPlacement {
+    fn from(line: Line<GridPlacement>) -> Self {
+        PyGridPlacement {
+            start: PyGridIndex::from(line.start),
+            end: PyGridIndex::from(line.end),
+        }
+    }
+}
+
+#[derive(FromPyObject, IntoPyObject)]
+pub struct PyGridTrackSize {
+    min_size: PyLength,
+    max_size: PyLength,
+}
+
+// Same as `length_field`, with the fields of the lengths named eg.
+// `grid_auto_rows[0].min_size`
+fn track_field(field: &str, size: PyGridTrackSize) -> PyResult<NonRepeatedTrackSizingFunction> {
+    Ok(NonRepeatedTrackSizingFunction {
+        min: length_field(&format!("{}.min_size", field), size.min_size)?,
+        max: length_field(&format!("{}.max_size", field), size.max_size)?,
+    })
+}
+
+impl From<NonRepeatedTrackSizingFunction> for PyGridTrackSize {
+    fn from(size: NonRepeatedTrackSizingFunction) -> PyGridTrackSize {
+        PyGridTrackSize {
+            min_size: PyLength::from(size.min),
+            max_size: PyLength::from(size.max),
+        }
+    }
+}
+
+impl TryFromIndex for GridTrackRepetition {
+    fn try_from_index(index: i32) -> Result<GridTrackRepetition, InvalidIndex> {
+        if index == -1 {
+            Ok(GridTrackRepetition::AutoFit)
+        } else if index == 0 {
+            Ok(GridTrackRepetition::AutoFill)
+        } else if index > 0 && index <= u16::MAX as i32 {
+            Ok(GridTrackRepetition::Count(index as u16))
+        } else {
+            Err(InvalidIndex(index))
+        }
+    }
+}
+
+impl ToIndex for GridTrackRepetition {
+    fn to_index(self) -> i32 {
+        match self {
+            GridTrackRepetition::AutoFit => -1,
+            GridTrackRepetition::AutoFill => 0,
+            GridTrackRepetition::Count(count) => count as i32,
+        }
+    }
+}
+
+#[derive(FromPyObject, IntoPyObject)]
+pub struct PyGridTrackSizing {
+    repetition: i32,
+    single: Option<PyGridTrackSize>,
+    repeat: Vec<PyGridTrackSize>,
+}
+
+// Converts a track of `grid_template_rows` or `grid_template_columns`, named eg.
+// `grid_template_rows[0]`, raising `ValueError` naming the track if it is invalid
+fn track_sizing_field(field: &str, value: PyGridTrackSizing) -> PyResult<TrackSizingFunction> {
+    Ok(if value.repetition == -2 {
+        let single = value
+            .single
+            .ok_or_else(|| PyValueError::new_err(format!("missing track size for {}", field)))?;
+        TrackSizingFunction::Single(track_field(field, single)?)
+    } else {
+        TrackSizingFunction::Repeat(
+            field_from_index(field, value.repetition)?,
+            value
+                .repeat
+                .into_iter()
+                .enumerate()
+                .map(|(i, size)| track_field(&format!("{}[{}]", field, i), size))
+                .collect::<PyResult<_>>()?,
+        )
+    })
+}
+
+impl From<TrackSizingFunction> for PyGridTrackSizing {
+    fn from(value: TrackSizingFunction) -> PyGridTrackSizing {
+        match value {
+            TrackSizingFunction::Single(size) => PyGridTrackSizing {
+                repetition: -2,
+                single: Some(PyGridTrackSize::from(size)),
+                repeat: Vec::new(),
+            },
+            TrackSizingFunction::Repeat(repetition, sizes) => PyGridTrackSizing {
+                repetition: repetition.to_index(),
+                single: None,
+                repeat: sizes.into_iter().map(PyGridTrackSize::from).collect(),
+            },
+        }
+    }
+}
+
+impl TryFrom<PyLength> for MinTrackSizingFunction {
+    type Error = InvalidIndex;
+
+    fn try_from(length: PyLength) -> Result<MinTrackSizingFunction, InvalidIndex> {
+        Ok(match length.dim {
+            0 => MinTrackSizingFunction::Auto,
+            1 => MinTrackSizingFunction::Fixed(LengthPercentage::Points(length.value)),
+            2 => MinTrackSizingFunction::Fixed(LengthPercentage::Percent(length.value)),
+            3 => MinTrackSizingFunction::MinContent,
+            4 => MinTrackSizingFunction::MaxContent,
+            _ => return Err(InvalidIndex(length.dim)),
+        })
+    }
+}
+
+impl From<MinTrackSizingFunction> for PyLength {
+    fn from(value: MinTrackSizingFunction) -> PyLength {
+        match value {
+            MinTrackSizingFunction::Auto => PyLength {
+                dim: 0,
+                value: f32::NAN,
+                offset: None,
+            },
+            MinTrackSizingFunction::Fixed(length) => PyLength::from(length),
+            MinTrackSizingFunction::MinContent => PyLength {
+                dim: 3,
+                value: f32::NAN,
+                offset: None,
+            },
+            MinTrackSizingFunction::MaxContent => PyLength {
+                dim: 4,
+                value: f32::NAN,
+                offset: None,
+            },
         }
     }
 }
 
-impl From<PySize> for Size<LengthPercentage> {
-    fn from(size: PySize) -> Self {
+impl TryFrom<PyLength> for MaxTrackSizingFunction {
+    type Error = InvalidIndex;
+
+    fn try_from(length: PyLength) -> Result<MaxTrackSizingFunction, InvalidIndex> {
+        Ok(match length.dim {
+            0 => MaxTrackSizingFunction::Auto,
+            1 => MaxTrackSizingFunction::Fixed(LengthPercentage::Points(length.value)),
+            2 => MaxTrackSizingFunction::Fixed(LengthPercentage::Percent(length.value)),
+            3 => MaxTrackSizingFunction::MinContent,
+            4 => MaxTrackSizingFunction::MaxContent,
+            5 => MaxTrackSizingFunction::FitContent(LengthPercentage::Points(length.value)),
+            6 => MaxTrackSizingFunction::FitContent(LengthPercentage::Percent(length.value)),
+            7 => MaxTrackSizingFunction::Fraction(length.value),
+            _ => return Err(InvalidIndex(length.dim)),
+        })
+    }
+}
+
+impl From<MaxTrackSizingFunction> for PyLength {
+    fn from(value: MaxTrackSizingFunction) -> PyLength {
+        match value {
+            MaxTrackSizingFunction::Auto => PyLength {
+                dim: 0,
+                value: f32::NAN,
+                offset: None,
+            },
+            MaxTrackSizingFunction::Fixed(length) => PyLength::from(length),
+            MaxTrackSizingFunction::MinContent => PyLength {
+                dim: 3,
+                value: f32::NAN,
+                offset: None,
+            },
+            MaxTrackSizingFunction::MaxContent => PyLength {
+                dim: 4,
+                value: f32::NAN,
+                offset: None,
+            },
+            MaxTrackSizingFunction::FitContent(LengthPercentage::Points(value)) => PyLength {
+                dim: 5,
+                value,
+                offset: None,
+            },
+            MaxTrackSizingFunction::FitContent(LengthPercentage::Percent(value)) => PyLength {
+                dim: 6,
+                value,
+                offset: None,
+            },
+            MaxTrackSizingFunction::Fraction(value) => PyLength {
+                dim: 7,
+                value,
+                offset: None,
+            },
+        }
+    }
+}
+
+#[derive(FromPyObject, IntoPyObject)]
+pub struct PyStyle {
+    // Layout mode/strategy
+    display: i32,
+    // Position
+    position: i32,
+    inset: PyRect,
+    // Alignment
+    align_items: Option<i32>,
+    justify_items: Option<i32>,
+    align_self: Option<i32>,
+    justify_self: Option<i32>,
+    align_content: Option<i32>,
+    justify_content: Option<i32>,
+    gap: PySize,
+    // Spacing
+    margin: PyRect,
+    border: PyRect,
+    padding: PyRect,
+    // Size
+    size: PySize,
+    min_size: PySize,
+    max_size: PySize,
+    aspect_ratio: Option<f32>,
+    // Flex
+    flex_wrap: i32,
+    flex_direction: i32,
+    flex_grow: f32,
+    flex_shrink: f32,
+    flex_basis: PyLength,
+    // Grid container properties
+    grid_template_rows: Vec<PyGridTrackSizing>,
+    grid_template_columns: Vec<PyGridTrackSizing>,
+    grid_auto_rows: Vec<PyGridTrackSize>,
+    grid_auto_columns: Vec<PyGridTrackSize>,
+    grid_auto_flow: i32,
+    // The line numbers of the named lines of the grid
+    grid_template_row_names: HashMap<String, i16>,
+    grid_template_column_names: HashMap<String, i16>,
+    // Grid child properties
+    grid_row: PyGridPlacement,
+    grid_column: PyGridPlacement,
+}
+
+// Raises `ValueError` naming the field for the first invalid index
+impl TryFrom<PyStyle> for Style {
+    type Error = PyErr;
+
+    fn try_from(style: PyStyle) -> PyResult<Style> {
+        let grid_template = |field, tracks: Vec<PyGridTrackSizing>| {
+            tracks
+                .into_iter()
+                .enumerate()
+                .map(|(i, sizing)| track_sizing_field(&format!("{}[{}]", field, i), sizing))
+                .collect::<PyResult<Vec<_>>>()
+        };
+        let grid_auto = |field, tracks: Vec<PyGridTrackSize>| {
+            tracks
+                .into_iter()
+                .enumerate()
+                .map(|(i, size)| track_field(&format!("{}[{}]", field, i), size))
+                .collect::<PyResult<Vec<_>>>()
+        };
+        Ok(Style {
+            // Layout mode/strategy
+            display: field_from_index("display", style.display)?,
+            // Position
+            position: field_from_index("position", style.position)?,
+            inset: rect_field("inset", style.inset)?,
+            // Alignment
+            align_items: optional_field_from_index("align_items", style.align_items)?,
+            justify_items: optional_field_from_index("justify_items", style.justify_items)?,
+            align_self: optional_field_from_index("align_self", style.align_self)?,
+            justify_self: optional_field_from_index("justify_self", style.justify_self)?,
+            align_content: optional_field_from_index("align_content", style.align_content)?,
+            justify_content: optional_field_from_index("justify_content", style.justify_content)?,
+            gap: size_field("gap", style.gap)?,
+            // Spacing
+            margin: rect_field("margin", style.margin)?,
+            border: rect_field("border", style.border)?,
+            padding: rect_field("padding", style.padding)?,
+            // Size
+            size: size_field("size", style.size)?,
+            min_size: size_field("min_size", style.min_size)?,
+            max_size: size_field("max_size", style.max_size)?,
+            aspect_ratio: style.aspect_ratio,
+            // Flex
+            flex_wrap: field_from_index("flex_wrap", style.flex_wrap)?,
+            flex_direction: field_from_index("flex_direction", style.flex_direction)?,
+            flex_grow: style.flex_grow,
+            flex_shrink: style.flex_shrink,
+            flex_basis: length_field("flex_basis", style.flex_basis)?,
+            // Grid container properties
+            grid_template_rows: grid_template("grid_template_rows", style.grid_template_rows)?,
+            grid_template_columns: grid_template(
+                "grid_template_columns",
+                style.grid_template_columns,
+            )?,
+            grid_auto_rows: grid_auto("grid_auto_rows", style.grid_auto_rows)?,
+            grid_auto_columns: grid_auto("grid_auto_columns", style.grid_auto_columns)?,
+            grid_auto_flow: field_from_index("grid_auto_flow", style.grid_auto_flow)?,
+            // Grid child properties
+            grid_row: placement_field("grid_row", style.grid_row)?,
+            grid_column: placement_field("grid_column", style.grid_column)?,
+        })
+    }
+}
+
+fn swap_left_right(style: &mut Style) {
+    mem::swap(&mut style.inset.left, &mut style.inset.right);
+    mem::swap(&mut style.margin.left, &mut style.margin.right);
+    mem::swap(&mut style.border.left, &mut style.border.right);
+    mem::swap(&mut style.padding.left, &mut style.padding.right);
+}
+
+// Converts the style, raising `ValueError` for values that taffy accepts but
+// which would result in undefined layouts. The calc lengths and grid line names
+// of the style are returned separately, as taffy does not support them.
+fn style_from_py(mut style: PyStyle) -> PyResult<(Style, StyleExtras)> {
+    if let Some(ratio) = style.aspect_ratio {
+        if !(ratio.is_finite() && ratio > 0.) {
+            return Err(PyValueError::new_err(format!(
+                "aspect_ratio must be positive and finite, not {}",
+                ratio
+            )));
+        }
+    }
+    if style.flex_basis.dim == 8 {
+        return Err(PyValueError::new_err(
+            "calc lengths are only supported for size, min_size and max_size",
+        ));
+    }
+    let calc = CalcStyle {
+        size: Calc::from_size(&style.size),
+        min_size: Calc::from_size(&style.min_size),
+        max_size: Calc::from_size(&style.max_size),
+    };
+    let line_names = LineNames {
+        rows: mem::take(&mut style.grid_template_row_names),
+        columns: mem::take(&mut style.grid_template_column_names),
+        row: Line {
+            start: style.grid_row.start.name.clone(),
+            end: style.grid_row.end.name.clone(),
+        },
+        column: Line {
+            start: style.grid_column.start.name.clone(),
+            end: style.grid_column.end.name.clone(),
+        },
+    };
+    let extras = StyleExtras { calc, line_names };
+    let invalid = invalid_length(&style);
+    let style = Style::try_from(style)?;
+    // Raised after converting the style, so that unsupported dimensions are
+    // reported as such
+    match invalid {
+        Some(err) => Err(err),
+        None => Ok((style, extras)),
+    }
+}
+
+// Returns the error for the first invalid length of the style, if any
+fn invalid_length(style: &PyStyle) -> Option<PyErr> {
+    style_lengths(style)
+        .into_iter()
+        .find_map(|(field, length)| {
+            // The dimensions without a value (eg. auto) use NAN for `value`
+            let finite = match length.dim {
+                1 | 2 | 5 | 6 | 7 | 8 => {
+                    length.value.is_finite() && length.offset.map_or(true, f32::is_finite)
+                }
+                _ => true,
+            };
+            // Taffy silently clamps negative spacing to zero
+            let spacing = ["gap.", "padding.", "border."];
+            if !finite {
+                Some(PyValueError::new_err(format!(
+                    "{} must be finite, not {}",
+                    field, length.value
+                )))
+            } else if length.value < 0. && spacing.iter().any(|prefix| field.starts_with(prefix)) {
+                Some(PyValueError::new_err(format!(
+                    "{} must not be negative, not {}",
+                    field, length.value
+                )))
+            } else {
+                None
+            }
+        })
+}
+
+// Returns the lengths of the style, with the name of the field of each (eg.
+// `padding.left` or `grid_template_rows[1].min_size`) for error messages
+fn style_lengths(style: &PyStyle) -> Vec<(String, &PyLength)> {
+    let mut lengths = Vec::new();
+    for (field, rect) in [
+        ("inset", &style.inset),
+        ("margin", &style.margin),
+        ("border", &style.border),
+        ("padding", &style.padding),
+    ] {
+        lengths.push((format!("{}.left", field), &rect.left));
+        lengths.push((format!("{}.right", field), &rect.right));
+        lengths.push((format!("{}.top", field), &rect.top));
+        lengths.push((format!("{}.bottom", field), &rect.bottom));
+    }
+    for (field, size) in [
+        ("gap", &style.gap),
+        ("size", &style.size),
+        ("min_size", &style.min_size),
+        ("max_size", &style.max_size),
+    ] {
+        lengths.push((format!("{}.width", field), &size.width));
+        lengths.push((format!("{}.height", field), &size.height));
+    }
+    lengths.push(("flex_basis".to_owned(), &style.flex_basis));
+    let mut tracks = Vec::new();
+    for (field, sizings) in [
+        ("grid_template_rows", &style.grid_template_rows),
+        ("grid_template_columns", &style.grid_template_columns),
+    ] {
+        for (i, sizing) in sizings.iter().enumerate() {
+            if let Some(track) = &sizing.single {
+                tracks.push((format!("{}[{}]", field, i), track));
+            }
+            for (j, track) in sizing.repeat.iter().enumerate() {
+                tracks.push((format!("{}[{}][{}]", field, i, j), track));
+            }
+        }
+    }
+    for (field, sizes) in [
+        ("grid_auto_rows", &style.grid_auto_rows),
+        ("grid_auto_columns", &style.grid_auto_columns),
+    ] {
+        for (i, track) in sizes.iter().enumerate() {
+            tracks.push((format!("{}[{}]", field, i), track));
+        }
+    }
+    for (field, track) in tracks {
+        lengths.push((format!("{}.min_size", field), &track.min_size));
+        lengths.push((format!("{}.max_size", field), &track.max_size));
+    }
+    lengths
+}
+
+// The parts of a style that taffy does not support, see `style_from_py`
+#[derive(Clone)]
+struct StyleExtras {
+    calc: CalcStyle,
+    line_names: LineNames,
+}
+
+impl Default for StyleExtras {
+    fn default() -> Self {
+        let no_calc = Size {
+            width: None,
+            height: None,
+        };
+        let no_name = Line {
+            start: None,
+            end: None,
+        };
+        StyleExtras {
+            calc: CalcStyle {
+                size: no_calc,
+                min_size: no_calc,
+                max_size: no_calc,
+            },
+            line_names: LineNames {
+                rows: HashMap::new(),
+                columns: HashMap::new(),
+                row: no_name.clone(),
+                column: no_name,
+            },
+        }
+    }
+}
+
+// A length of `percent` of the parent plus `offset` points, ie. CSS
+// `calc(percent + offset)`
+#[derive(Clone, Copy, PartialEq)]
+struct Calc {
+    percent: f32,
+    offset: f32,
+}
+
+impl Calc {
+    fn from_length(length: &PyLength) -> Option<Calc> {
+        match length.dim {
+            8 => Some(Calc {
+                percent: length.value,
+                offset: length.offset.unwrap_or(0.),
+            }),
+            _ => None,
+        }
+    }
+
+    fn from_size(size: &PySize) -> Size<Option<Calc>> {
         Size {
-            height: LengthPercentage::from(size.height),
-            width: LengthPercentage::from(size.width),
+            width: Calc::from_length(&size.width),
+            height: Calc::from_length(&size.height),
         }
     }
+
+    fn to_length(self) -> PyLength {
+        PyLength {
+            dim: 8,
+            value: self.percent,
+            offset: Some(self.offset),
+        }
+    }
+
+    // Percentages of an indefinite size cannot be resolved, which taffy handles
+    // the same way as `auto`
+    fn resolve(self, parent_size: Option<f32>) -> Dimension {
+        match parent_size {
+            Some(size) => Dimension::Points(self.percent * size + self.offset),
+            None => Dimension::Percent(self.percent),
+        }
+    }
+}
+
+// The calc lengths of a style (`None` for lengths that are not calc lengths)
+#[derive(Clone, Copy, PartialEq)]
+struct CalcStyle {
+    size: Size<Option<Calc>>,
+    min_size: Size<Option<Calc>>,
+    max_size: Size<Option<Calc>>,
+}
+
+impl CalcStyle {
+    fn fields(&self) -> [Size<Option<Calc>>; 3] {
+        [self.size, self.min_size, self.max_size]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fields()
+            .iter()
+            .all(|size| size.width.is_none() && size.height.is_none())
+    }
+
+    // Replaces the lengths of `style` (converted from the taffy style) with the
+    // calc lengths
+    fn apply(&self, style: &mut PyStyle) {
+        let sizes = [&mut style.size, &mut style.min_size, &mut style.max_size];
+        for (size, calc) in sizes.into_iter().zip(self.fields()) {
+            if let Some(calc) = calc.width {
+                size.width = calc.to_length();
+            }
+            if let Some(calc) = calc.height {
+                size.height = calc.to_length();
+            }
+        }
+    }
+
+    // Sets the lengths of `style` to the calc lengths resolved against the size
+    // of the parent
+    fn resolve(&self, style: &mut Style, parent_size: Size<Option<f32>>) {
+        let sizes = [&mut style.size, &mut style.min_size, &mut style.max_size];
+        for (size, calc) in sizes.into_iter().zip(self.fields()) {
+            if let Some(calc) = calc.width {
+                size.width = calc.resolve(parent_size.width);
+            }
+            if let Some(calc) = calc.height {
+                size.height = calc.resolve(parent_size.height);
+            }
+        }
+    }
+}
+
+// The grid line names of a style
+#[derive(Clone)]
+struct LineNames {
+    // The line numbers of the named lines of the grid
+    rows: HashMap<String, i16>,
+    columns: HashMap<String, i16>,
+    // The names of the lines the grid item is placed at
+    row: Line<Option<String>>,
+    column: Line<Option<String>>,
+}
+
+impl LineNames {
+    fn is_placed(&self) -> bool {
+        [&self.row, &self.column]
+            .iter()
+            .any(|line| line.start.is_some() || line.end.is_some())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.is_empty() && self.columns.is_empty() && !self.is_placed()
+    }
+
+    // Adds the line names to `style` (converted from the taffy style)
+    fn apply(&self, style: &mut PyStyle) {
+        style.grid_template_row_names = self.rows.clone();
+        style.grid_template_column_names = self.columns.clone();
+        let indices = [
+            (&mut style.grid_row.start, &self.row.start),
+            (&mut style.grid_row.end, &self.row.end),
+            (&mut style.grid_column.start, &self.column.start),
+            (&mut style.grid_column.end, &self.column.end),
+        ];
+        for (index, name) in indices {
+            if let Some(name) = name {
+                *index = PyGridIndex {
+                    kind: 1,
+                    value: 0,
+                    name: Some(name.clone()),
+                };
+            }
+        }
+    }
+
+    // Places the grid item at the named lines of the parent. As in CSS, the
+    // lines `{name}-start` and `{name}-end` (such as the lines of a grid area)
+    // are used for the start and end before a line named `{name}`.
+    fn resolve(&self, style: &mut Style, parent: Option<&LineNames>) {
+        let resolve = |lines: Option<&HashMap<String, i16>>, name: &str, suffix: &str| {
+            lines
+                .and_then(|lines| {
+                    lines
+                        .get(&format!("{name}-{suffix}"))
+                        .or_else(|| lines.get(name))
+                })
+                .map_or(GridPlacement::Auto, |line| {
+                    GridPlacement::from_line_index(*line)
+                })
+        };
+        let placements = [
+            (
+                &mut style.grid_row,
+                &self.row,
+                parent.map(|names| &names.rows),
+            ),
+            (
+                &mut style.grid_column,
+                &self.column,
+                parent.map(|names| &names.columns),
+            ),
+        ];
+        for (placement, names, lines) in placements {
+            if let Some(name) = &names.start {
+                placement.start = resolve(lines, name, "start");
+            }
+            if let Some(name) = &names.end {
+                placement.end = resolve(lines, name, "end");
+            }
+        }
+    }
+}
+
+impl From<&Style> for PyStyle {
+    fn from(style: &Style) -> PyStyle {
+        PyStyle {
+            // Layout mode/strategy
+            display: style.display.to_index(),
+            // Position
+            position: style.position.to_index(),
+            inset: PyRect::from(style.inset),
+            // Alignment
+            align_items: style.align_items.map(ToIndex::to_index),
+            justify_items: style.justify_items.map(ToIndex::to_index),
+            align_self: style.align_self.map(ToIndex::to_index),
+            justify_self: style.justify_self.map(ToIndex::to_index),
+            align_content: style.align_content.map(ToIndex::to_index),
+            justify_content: style.justify_content.map(ToIndex::to_index),
+            gap: PySize::from(style.gap),
+            // Spacing
+            margin: PyRect::from(style.margin),
+            border: PyRect::from(style.border),
+            padding: PyRect::from(style.padding),
+            // Size
+            size: PySize::from(style.size),
+            min_size: PySize::from(style.min_size),
+            max_size: PySize::from(style.max_size),
+            aspect_ratio: style.aspect_ratio,
+            // Flex
+            flex_wrap: style.flex_wrap.to_index(),
+            flex_direction: style.flex_direction.to_index(),
+            flex_grow: style.flex_grow,
+            flex_shrink: style.flex_shrink,
+            flex_basis: PyLength::from(style.flex_basis),
+            // Grid container properties
+            grid_template_rows: style
+                .grid_template_rows
+                .iter()
+                .cloned()
+                .map(PyGridTrackSizing::from)
+                .collect(),
+            grid_template_columns: style
+                .grid_template_columns
+                .iter()
+                .cloned()
+                .map(PyGridTrackSizing::from)
+                .collect(),
+            grid_auto_rows: style
+                .grid_auto_rows
+                .iter()
+                .copied()
+                .map(PyGridTrackSize::from)
+                .collect(),
+            grid_auto_columns: style
+                .grid_auto_columns
+                .iter()
+                .copied()
+                .map(PyGridTrackSize::from)
+                .collect(),
+            grid_auto_flow: style.grid_auto_flow.to_index(),
+            grid_template_row_names: HashMap::new(),
+            grid_template_column_names: HashMap::new(),
+            // Grid child properties
+            grid_row: PyGridPlacement::from(style.grid_row),
+            grid_column: PyGridPlacement::from(style.grid_column),
+        }
+    }
+}
+
+// NODES
+
+// Creates a node with the style, or the default style of the tree if no style
+// is given (see `set_default_style`)
+#[pyfunction]
+#[pyo3(signature = (taffy_ptr, style=None))]
+fn node_create(taffy_ptr: u64, style: Option<PyStyle>) -> PyResult<u64> {
+    with_tree(taffy_ptr, |tree| {
+        let (style, extras) = match style {
+            Some(style) => style_from_py(style)?,
+            None => tree.default_style.clone(),
+        };
+        let node = tree
+            .taffy
+            .new_leaf(tree.directed_style(style))
+            .map_err(taffy_error)?;
+        tree.nodes.insert(node);
+        tree.set_extras(node, extras);
+        Ok(node_to_id(node))
+    })
+}
+
+#[pyfunction]
+fn node_create_default(taffy_ptr: u64) -> PyResult<u64> {
+    node_create(taffy_ptr, None)
+}
+
+// Sets the style of nodes created without a style. The default style is only
+// used in place of a style, ie. there is no cascade: an explicit style replaces
+// the default style entirely, and changing the default style does not change
+// the style of existing nodes.
+#[pyfunction]
+fn set_default_style(taffy_ptr: u64, style: PyStyle) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        tree.default_style = style_from_py(style)?;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn nodes_create(taffy_ptr: u64, styles: Vec<PyStyle>) -> PyResult<Vec<u64>> {
+    with_tree(taffy_ptr, |tree| {
+        // Convert all styles first, so that no nodes are created if any is invalid
+        let styles = styles
+            .into_iter()
+            .map(style_from_py)
+            .collect::<PyResult<Vec<(Style, StyleExtras)>>>()?;
+        let mut node_ids = Vec::with_capacity(styles.len());
+        for (style, extras) in styles {
+            let node = tree
+                .taffy
+                .new_leaf(tree.directed_style(style))
+                .map_err(taffy_error)?;
+            tree.nodes.insert(node);
+            tree.set_extras(node, extras);
+            node_ids.push(node_to_id(node));
+        }
+        Ok(node_ids)
+    })
+}
+
+#[pyfunction]
+fn node_create_with_children(taffy_ptr: u64, style: PyStyle, children: Vec<u64>) -> PyResult<u64> {
+    with_tree(taffy_ptr, |tree| {
+        let (style, extras) = style_from_py(style)?;
+        // Look up all children first, so that no node is created if any id is
+        // invalid
+        let children = children
+            .into_iter()
+            .map(|child_id| lookup(tree, child_id))
+            .collect::<PyResult<Vec<Node>>>()?;
+        let node = tree
+            .taffy
+            .new_with_children(tree.directed_style(style), &children)
+            .map_err(taffy_error)?;
+        tree.nodes.insert(node);
+        tree.set_extras(node, extras);
+        Ok(node_to_id(node))
+    })
+}
+
+#[pyfunction]
+fn node_clone_subtree(taffy_ptr: u64, root_id: u64) -> PyResult<u64> {
+    with_tree(taffy_ptr, |tree| {
+        let root = lookup(tree, root_id)?;
+        let clone = tree.clone_subtree(root).map_err(taffy_error)?;
+        Ok(node_to_id(clone))
+    })
+}
+
+#[pyfunction]
+fn node_add_child(taffy_ptr: u64, node_id: u64, child_id: u64) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let child = lookup(tree, child_id)?;
+        tree.taffy.add_child(node, child).map_err(taffy_error)
+    })
+}
+
+#[pyfunction]
+fn node_set_children(taffy_ptr: u64, node_id: u64, children: Vec<u64>) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        // Look up all children before changing anything, so an invalid id leaves
+        // the children of `node` untouched
+        let children = children
+            .into_iter()
+            .map(|child_id| lookup(tree, child_id))
+            .collect::<PyResult<Vec<Node>>>()?;
+        tree.taffy
+            .set_children(node, &children)
+            .map_err(taffy_error)
+    })
+}
+
+#[pyfunction]
+fn node_reparent(taffy_ptr: u64, node_id: u64, new_parent_id: u64, index: usize) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let new_parent = lookup(tree, new_parent_id)?;
+        tree.move_node(node, new_parent, index)
+    })
+}
+
+#[pyfunction]
+fn node_drop(taffy_ptr: u64, node_id: u64) -> PyResult<()> {
+    // Remove a specific node from the tree and drop it
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        tree.drop_node(node);
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_clear_children(taffy_ptr: u64, node_id: u64) -> PyResult<()> {
+    // Remove all children of a node and drop them (but not their children)
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let children = tree.taffy.children(node).map_err(taffy_error)?;
+        tree.taffy.set_children(node, &[]).map_err(taffy_error)?;
+        for child in children {
+            tree.drop_node(child);
+        }
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_drop_all(taffy_ptr: u64) -> PyResult<()> {
+    // Drops all nodes in the tree
+    with_tree(taffy_ptr, |tree| {
+        tree.taffy.clear();
+        tree.nodes.clear();
+        tree.measured.clear();
+        tree.rounded_layouts.clear();
+        tree.calc.clear();
+        tree.line_names.clear();
+        tree.available_spaces.clear();
+        tree.rounding_scales.clear();
+        tree.scroll_offsets.clear();
+        tree.scroll_paddings.clear();
+        tree.keys.clear();
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_exists(taffy_ptr: u64, node_id: u64) -> PyResult<bool> {
+    with_tree_ref(taffy_ptr, |tree| Ok(lookup(tree, node_id).is_ok()))
+}
+
+#[pyfunction]
+fn node_replace_child_at_index(
+    taffy_ptr: u64,
+    node_id: u64,
+    index: usize,
+    child_id: u64,
+) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let child = lookup(tree, child_id)?;
+        tree.taffy
+            .replace_child_at_index(node, index, child)
+            .map_err(taffy_error)?;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_swap_children(taffy_ptr: u64, node_id: u64, i: usize, j: usize) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        for index in [i, j] {
+            tree.taffy
+                .child_at_index(node, index)
+                .map_err(index_error)?;
+        }
+        // Replacing the children one at a time would detach the first one
+        // from the node, so set all children at once instead
+        let mut children = tree.taffy.children(node).map_err(taffy_error)?;
+        children.swap(i, j);
+        tree.taffy
+            .set_children(node, &children)
+            .map_err(taffy_error)
+    })
+}
+
+// The child is removed from its current parent first, if any (the same as
+// `node_reparent`), so that it is never listed by two parents.
+#[pyfunction]
+fn node_insert_child_at_index(
+    taffy_ptr: u64,
+    node_id: u64,
+    index: usize,
+    child_id: u64,
+) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let child = lookup(tree, child_id)?;
+        tree.move_node(child, node, index)
+    })
+}
+
+#[pyfunction]
+fn node_remove_child(taffy_ptr: u64, node_id: u64, child_id: u64) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let child = lookup(tree, child_id)?;
+        // Taffy panics if `child` is not a child of `node`
+        if tree.taffy.parent(child) != Some(node) {
+            return Err(taffy_error(TaffyError::InvalidChildNode(child)));
+        }
+        tree.taffy.remove_child(node, child).map_err(taffy_error)?;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_detach(taffy_ptr: u64, node_id: u64) -> PyResult<()> {
+    // Remove the node from its parent, keeping it (and its subtree) as a root
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let parent = tree
+            .taffy
+            .parent(node)
+            .ok_or_else(|| PyValueError::new_err(format!("node {} has no parent", node_id)))?;
+        tree.taffy.remove_child(parent, node).map_err(taffy_error)?;
+        Ok(())
+    })
+}
+
+// Replaces the node with `new_root_id` (and its subtree) under the parent of the
+// node, at the same index. The new root is first removed from its own parent, if
+// any. The node is kept as a root with its subtree, unless `drop` is true, in
+// which case the node and its descendants are dropped.
+#[pyfunction]
+#[pyo3(signature = (taffy_ptr, node_id, new_root_id, drop=false))]
+fn node_replace_subtree(
+    taffy_ptr: u64,
+    node_id: u64,
+    new_root_id: u64,
+    drop: bool,
+) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let new_root = lookup(tree, new_root_id)?;
+        let parent = tree
+            .taffy
+            .parent(node)
+            .ok_or_else(|| PyValueError::new_err(format!("node {} has no parent", node_id)))?;
+        // Moving a node into its own subtree would create a cycle
+        let mut ancestor = Some(parent);
+        while let Some(n) = ancestor {
+            if n == new_root {
+                return Err(PyValueError::new_err(format!(
+                    "node {} cannot be moved into its own subtree",
+                    new_root_id
+                )));
+            }
+            ancestor = tree.taffy.parent(n);
+        }
+        if new_root == node {
+            return Ok(());
+        }
+        if let Some(old_parent) = tree.taffy.parent(new_root) {
+            tree.taffy
+                .remove_child(old_parent, new_root)
+                .map_err(taffy_error)?;
+        }
+        let children = tree.taffy.children(parent).map_err(taffy_error)?;
+        let index = children.iter().position(|child| *child == node).unwrap();
+        tree.taffy
+            .replace_child_at_index(parent, index, new_root)
+            .map_err(taffy_error)?;
+        if drop {
+            for n in tree.subtree(node) {
+                tree.drop_node(n);
+            }
+        }
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_remove_child_at_index(taffy_ptr: u64, node_id: u64, index: usize) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        tree.taffy
+            .remove_child_at_index(node, index)
+            .map_err(taffy_error)?;
+        Ok(())
+    })
+}
+
+#[pyfunction]
+fn node_children(taffy_ptr: u64, node_id: u64) -> PyResult<Vec<u64>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let children = tree
+            .taffy
+            .children(lookup(tree, node_id)?)
+            .map_err(taffy_error)?;
+        Ok(children.into_iter().map(node_to_id).collect())
+    })
+}
+
+#[pyfunction]
+fn node_child_count(taffy_ptr: u64, node_id: u64) -> PyResult<usize> {
+    with_tree_ref(taffy_ptr, |tree| {
+        tree.taffy
+            .child_count(lookup(tree, node_id)?)
+            .map_err(taffy_error)
+    })
+}
+
+#[pyfunction]
+fn node_child_at_index(taffy_ptr: u64, node_id: u64, index: usize) -> PyResult<u64> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let child = tree
+            .taffy
+            .child_at_index(lookup(tree, node_id)?, index)
+            .map_err(index_error)?;
+        Ok(node_to_id(child))
+    })
+}
+
+#[pyfunction]
+fn node_parent(taffy_ptr: u64, node_id: u64) -> PyResult<Option<u64>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.taffy.parent(lookup(tree, node_id)?).map(node_to_id))
+    })
 }
 
-impl From<PySize> for Size<AvailableSpace> {
-    fn from(size: PySize) -> Self {
-        Size {
-            height: AvailableSpace::from(size.height),
-            width: AvailableSpace::from(size.width),
+#[pyfunction]
+fn node_is_root(taffy_ptr: u64, node_id: u64) -> PyResult<bool> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.taffy.parent(lookup(tree, node_id)?).is_none())
+    })
+}
+
+#[pyfunction]
+fn node_depth(taffy_ptr: u64, node_id: u64) -> PyResult<usize> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let mut depth = 0;
+        let mut parent = tree.taffy.parent(lookup(tree, node_id)?);
+        while let Some(node) = parent {
+            depth += 1;
+            parent = tree.taffy.parent(node);
         }
-    }
+        Ok(depth)
+    })
 }
 
-#[derive(FromPyObject, IntoPyObject)]
-pub struct PyRect {
-    left: PyLength,
-    right: PyLength,
-    top: PyLength,
-    bottom: PyLength,
+#[pyfunction]
+fn node_dirty(taffy_ptr: u64, node_id: u64) -> PyResult<bool> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.taffy.dirty(lookup(tree, node_id)?).unwrap())
+    })
 }
 
-impl From<PyRect> for Rect<LengthPercentage> {
-    fn from(rect: PyRect) -> Rect<LengthPercentage> {
-        Rect {
-            left: LengthPercentage::from(rect.left),
-            right: LengthPercentage::from(rect.right),
-            top: LengthPercentage::from(rect.top),
-            bottom: LengthPercentage::from(rect.bottom),
-        }
-    }
+#[pyfunction]
+fn node_mark_dirty(taffy_ptr: u64, node_id: u64) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        tree.taffy.mark_dirty(lookup(tree, node_id)?).unwrap();
+        Ok(())
+    })
 }
 
-impl From<PyRect> for Rect<LengthPercentageAuto> {
-    fn from(rect: PyRect) -> Rect<LengthPercentageAuto> {
-        Rect {
-            left: LengthPercentageAuto::from(rect.left),
-            right: LengthPercentageAuto::from(rect.right),
-            top: LengthPercentageAuto::from(rect.top),
-            bottom: LengthPercentageAuto::from(rect.bottom),
-        }
-    }
+// Returns whether the node or any of its descendants is dirty. Taffy marks the
+// ancestors of a dirty node as dirty as well, but not the other way round. The
+// descendants of hidden nodes are skipped, as taffy does not lay them out (so
+// they can still be dirty after computing the layout).
+#[pyfunction]
+fn node_dirty_recursive(taffy_ptr: u64, node_id: u64) -> PyResult<bool> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.dirty_recursive(lookup(tree, node_id)?))
+    })
 }
 
-impl From<PyRect> for Rect<Dimension> {
-    fn from(rect: PyRect) -> Rect<Dimension> {
-        Rect {
-            left: Dimension::from(rect.left),
-            right: Dimension::from(rect.right),
-            top: Dimension::from(rect.top),
-            bottom: Dimension::from(rect.bottom),
-        }
-    }
+#[pyfunction]
+fn subtree_dirty_count(taffy_ptr: u64, root_id: u64) -> PyResult<usize> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let nodes = tree.subtree(lookup(tree, root_id)?);
+        Ok(nodes
+            .into_iter()
+            .filter(|node| tree.taffy.dirty(*node).unwrap())
+            .count())
+    })
 }
 
-#[derive(FromPyObject, IntoPyObject)]
-pub struct PyGridIndex {
-    kind: i8,
-    value: i16,
+// Returns the number of nodes without children in the subtree of the node
+// (including the node itself, if it has no children)
+#[pyfunction]
+fn subtree_leaf_count(taffy_ptr: u64, root_id: u64) -> PyResult<usize> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let nodes = tree.subtree(lookup(tree, root_id)?);
+        Ok(nodes
+            .into_iter()
+            .filter(|node| tree.taffy.child_count(*node).unwrap() == 0)
+            .count())
+    })
 }
 
-impl From<PyGridIndex> for GridPlacement {
-    fn from(grid_index: PyGridIndex) -> Self {
-        match grid_index.kind {
-            1 => Self::from_line_index(grid_index.value),
-            2 => Self::from_span(grid_index.value as u16),
-            _ => Self::Auto,
+// Returns the ids of the node and its descendants in the order they should be
+// painted (back to front): parents before their children, and siblings as in
+// `Tree::paint_order`.
+#[pyfunction]
+fn subtree_paint_order(taffy_ptr: u64, root_id: u64) -> PyResult<Vec<u64>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let mut node_ids = Vec::new();
+        let mut stack = vec![lookup(tree, root_id)?];
+        while let Some(node) = stack.pop() {
+            node_ids.push(node_to_id(node));
+            stack.extend(tree.paint_order(node).into_iter().rev());
         }
-    }
+        Ok(node_ids)
+    })
 }
 
-#[derive(FromPyObject, IntoPyObject)]
-pub struct PyGridPlacement {
-    start: PyGridIndex,
-    end: PyGridIndex,
+#[pyfunction]
+fn node_set_style(taffy_ptr: u64, node_id: u64, style: PyStyle) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let (style, extras) = style_from_py(style)?;
+        let node = lookup(tree, node_id)?;
+        tree.taffy
+            .set_style(node, tree.directed_style(style))
+            .unwrap();
+        tree.set_extras(node, extras);
+        Ok(())
+    })
 }
 
-impl From<PyGridPlacement> for Line<GridPlacement> {
-    fn from(grid_placement: PyGridPlacement) -> Self {
-        Self {
-            start: GridPlacement::from(grid_placement.start),
-            end: GridPlacement::from(grid_placement.end),
+// Same as `node_set_style` for each of the nodes, in a single call. All nodes
+// and styles are checked before any style is changed.
+#[pyfunction]
+fn nodes_set_styles(taffy_ptr: u64, updates: Vec<(u64, PyStyle)>) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let updates = updates
+            .into_iter()
+            .map(|(node_id, style)| Ok((lookup(tree, node_id)?, style_from_py(style)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        for (node, (style, extras)) in updates {
+            tree.taffy
+                .set_style(node, tree.directed_style(style))
+                .unwrap();
+            tree.set_extras(node, extras);
         }
-    }
+        Ok(())
+    })
 }
 
-#[derive(FromPyObject, IntoPyObject)]
-pub struct PyGridTrackSize {
-    min_size: PyLength,
-    max_size: PyLength,
+#[pyfunction]
+fn node_get_style(taffy_ptr: u64, node_id: u64) -> PyResult<PyStyle> {
+    with_tree_ref(taffy_ptr, |tree| Ok(tree.py_style(lookup(tree, node_id)?)))
 }
 
-impl From<PyGridTrackSize> for NonRepeatedTrackSizingFunction {
-    fn from(size: PyGridTrackSize) -> NonRepeatedTrackSizingFunction {
-        NonRepeatedTrackSizingFunction {
-            min: MinTrackSizingFunction::from(size.min_size),
-            max: MaxTrackSizingFunction::from(size.max_size),
-        }
-    }
+// Sets the key of the node (or removes it, if `None`), which is shown by
+// `node_print_tree` and included by `tree_to_json`. Keys need not be unique.
+#[pyfunction]
+#[pyo3(signature = (taffy_ptr, node_id, key=None))]
+fn node_set_key(taffy_ptr: u64, node_id: u64, key: Option<String>) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        match key {
+            Some(key) => tree.keys.insert(node, key),
+            None => tree.keys.remove(&node),
+        };
+        Ok(())
+    })
 }
 
-impl FromIndex<GridTrackRepetition> for GridTrackRepetition {
-    fn from_index(index: i32) -> GridTrackRepetition {
-        if index == -1 {
-            GridTrackRepetition::AutoFit
-        } else if index == 0 {
-            GridTrackRepetition::AutoFill
-        } else if index > 0 {
-            GridTrackRepetition::Count(index as u16)
-        } else {
-            panic!("invalid index {}", index)
-        }
-    }
+#[pyfunction]
+fn node_get_key(taffy_ptr: u64, node_id: u64) -> PyResult<Option<String>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.keys.get(&lookup(tree, node_id)?).cloned())
+    })
 }
 
-#[derive(FromPyObject, IntoPyObject)]
-pub struct PyGridTrackSizing {
-    repetition: i32,
-    single: Option<PyGridTrackSize>,
-    repeat: Vec<PyGridTrackSize>,
+// Returns the node with the key, or the one with the lowest id if several nodes
+// have the key (`None` if there is none).
+#[pyfunction]
+fn find_node_by_key(taffy_ptr: u64, key: &str) -> PyResult<Option<u64>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree
+            .keys
+            .iter()
+            .filter(|(_, k)| k.as_str() == key)
+            .map(|(node, _)| node_to_id(*node))
+            .min())
+    })
 }
 
-impl From<PyGridTrackSizing> for TrackSizingFunction {
-    fn from(value: PyGridTrackSizing) -> TrackSizingFunction {
-        if value.repetition == -2 {
-            TrackSizingFunction::Single(NonRepeatedTrackSizingFunction::from(value.single.unwrap()))
-        } else {
-            TrackSizingFunction::Repeat(
-                GridTrackRepetition::from_index(value.repetition),
-                value
-                    .repeat
-                    .into_iter()
-                    .map(|e| NonRepeatedTrackSizingFunction::from(e))
-                    .collect(),
-            )
-        }
-    }
+// If `strict` is true, an exception raised by a `measure` function is raised
+// from here once the computation is done (and further `measure` calls are
+// skipped), instead of being logged and `NAN, NAN` used in place.
+// Failures raise an exception, so the return value is always `true` (kept for
+// backwards compatibility).
+//
+// No nodes are marked dirty when only the available space changes, so the sizes
+// cached by taffy are reused for nodes that are measured with the same
+// constraints as before (eg. when only the height changes in a row).
+#[pyfunction]
+#[pyo3(signature = (taffy_ptr, node_id, available_space, strict=false))]
+fn node_compute_layout(
+    py: Python,
+    taffy_ptr: u64,
+    node_id: u64,
+    available_space: PySize,
+    strict: bool,
+) -> PyResult<bool> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        tree.compute_layout(
+            py,
+            node,
+            size_field("available_space", available_space)?,
+            strict,
+        )?;
+        Ok(true)
+    })
 }
 
-impl From<PyLength> for MinTrackSizingFunction {
-    fn from(length: PyLength) -> MinTrackSizingFunction {
-        match length.dim {
-            0 => MinTrackSizingFunction::Auto,
-            1 => MinTrackSizingFunction::Fixed(LengthPercentage::Points(length.value)),
-            2 => MinTrackSizingFunction::Fixed(LengthPercentage::Percent(length.value)),
-            3 => MinTrackSizingFunction::MinContent,
-            4 => MinTrackSizingFunction::MaxContent,
-            _ => panic!("unsupported dimension {}", length.dim),
+// Same as `node_compute_layout`, but only computes the layout if the node or any
+// of its descendants is dirty, if the available space changed since the layout
+// of the node was last computed (see `node_last_available_space`), or if
+// rounding has been enabled or disabled (or the rounding scale changed) since.
+// Returns whether the layout was computed.
+#[pyfunction]
+fn node_compute_layout_if_dirty(
+    py: Python,
+    taffy_ptr: u64,
+    node_id: u64,
+    available_space: PySize,
+) -> PyResult<bool> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let available_space = size_field("available_space", available_space)?;
+        if !tree.dirty_recursive(node)
+            && tree.available_spaces.get(&node) == Some(&available_space)
+            && tree.use_rounding == tree.rounded_layouts.contains_key(&node)
+            && (!tree.use_rounding || tree.rounding_scales.get(&node) == Some(&tree.rounding_scale))
+        {
+            return Ok(false);
         }
-    }
+        tree.compute_layout(py, node, available_space, false)?;
+        Ok(true)
+    })
 }
 
-impl From<PyLength> for MaxTrackSizingFunction {
-    fn from(length: PyLength) -> MaxTrackSizingFunction {
-        match length.dim {
-            0 => MaxTrackSizingFunction::Auto,
-            1 => MaxTrackSizingFunction::Fixed(LengthPercentage::Points(length.value)),
-            2 => MaxTrackSizingFunction::Fixed(LengthPercentage::Percent(length.value)),
-            3 => MaxTrackSizingFunction::MinContent,
-            4 => MaxTrackSizingFunction::MaxContent,
-            5 => MaxTrackSizingFunction::FitContent(LengthPercentage::Points(length.value)),
-            6 => MaxTrackSizingFunction::FitContent(LengthPercentage::Percent(length.value)),
-            7 => MaxTrackSizingFunction::Fraction(length.value),
-            _ => panic!("unsupported dimension {}", length.dim),
-        }
-    }
+// Returns the min-content and max-content widths of the node, without changing
+// the current layout, see `Tree::intrinsic_widths`
+#[pyfunction]
+fn node_intrinsic_size(py: Python, taffy_ptr: u64, node_id: u64) -> PyResult<(f32, f32)> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        tree.intrinsic_widths(py, node)
+    })
 }
 
+// Returns the size of the node for the given available width and height, eg. a
+// definite width and a max-content height, without changing the current layout
 #[pyfunction]
-fn style_drop(style_ptr: usize) {
-    let _style = unsafe { Box::from_raw(style_ptr as *mut Style) };
+fn node_measure(
+    py: Python,
+    taffy_ptr: u64,
+    node_id: u64,
+    width: PyLength,
+    height: PyLength,
+) -> PyResult<(f32, f32)> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let available_space = Size {
+            width: length_field("width", width)?,
+            height: length_field("height", height)?,
+        };
+        let size = tree.measure(py, node, &[available_space])?[0];
+        Ok((size.width, size.height))
+    })
 }
 
+// Computes the layouts of several roots, each with its own available space, and
+// returns whether each layout was computed. A failed layout (eg. a `measure`
+// function raising with `strict`, see `node_compute_layout`) is logged and
+// marked dirty, and the remaining roots are still computed.
 #[pyfunction]
-fn style_create(
-    // Layout mode/strategy
-    display: i32,
-    // Position
-    position: i32,
-    inset: PyRect,
-    // Alignment
-    gap: PySize,
-    // Spacing
-    margin: PyRect,
-    border: PyRect,
-    padding: PyRect,
-    // Size
-    size: PySize,
-    min_size: PySize,
-    max_size: PySize,
-    // Flex
-    flex_wrap: i32,
-    flex_direction: i32,
-    flex_grow: f32,
-    flex_shrink: f32,
-    flex_basis: PyLength,
-    // Grid container properties
-    grid_template_rows: Vec<PyGridTrackSizing>,
-    grid_template_columns: Vec<PyGridTrackSizing>,
-    grid_auto_rows: Vec<PyGridTrackSize>,
-    grid_auto_columns: Vec<PyGridTrackSize>,
-    grid_auto_flow: i32,
-    // Grid child properties
-    grid_row: PyGridPlacement,
-    grid_column: PyGridPlacement,
-    // Size, optional
-    aspect_ratio: Option<f32>,
-    // Alignment, optional
-    align_items: Option<i32>,
-    justify_items: Option<i32>,
-    align_self: Option<i32>,
-    justify_self: Option<i32>,
-    align_content: Option<i32>,
-    justify_content: Option<i32>,
-) -> usize {
-    let style = Style {
-        // Layout mode/strategy
-        display: Display::from_index(display),
-        // Position
-        position: Position::from_index(position),
-        inset: Rect::from(inset) as Rect<LengthPercentageAuto>,
-        // Alignment
-        align_items: AlignItems::from_index(align_items),
-        justify_items: JustifyItems::from_index(justify_items),
-        align_self: AlignSelf::from_index(align_self),
-        justify_self: JustifySelf::from_index(justify_self),
-        align_content: AlignContent::from_index(align_content),
-        justify_content: JustifyContent::from_index(justify_content),
-        gap: Size::from(gap),
-        // Spacing
-        margin: Rect::from(margin),
-        border: Rect::from(border),
-        padding: Rect::from(padding),
-        // Size
-        size: Size::from(size),
-        min_size: Size::from(min_size),
-        max_size: Size::from(max_size),
-        aspect_ratio: aspect_ratio,
-        // Flex
-        flex_wrap: FlexWrap::from_index(flex_wrap),
-        flex_direction: FlexDirection::from_index(flex_direction),
-        flex_grow: flex_grow,
-        flex_shrink: flex_shrink,
-        flex_basis: Dimension::from(flex_basis),
-        // Grid container properties
-        grid_template_rows: grid_template_rows
-            .into_iter()
-            .map(|e| TrackSizingFunction::from(e))
-            .collect(),
-        grid_template_columns: grid_template_columns
+#[pyo3(signature = (taffy_ptr, roots, strict=false))]
+fn compute_layouts(
+    py: Python,
+    taffy_ptr: u64,
+    roots: Vec<(u64, PySize)>,
+    strict: bool,
+) -> PyResult<Vec<bool>> {
+    with_tree(taffy_ptr, |tree| {
+        // Look up all roots before computing any layouts
+        let roots = roots
             .into_iter()
-            .map(|e| TrackSizingFunction::from(e))
-            .collect(),
-        grid_auto_rows: grid_auto_rows
+            .map(|(node_id, available_space)| {
+                Ok((
+                    lookup(tree, node_id)?,
+                    size_field("available_space", available_space)?,
+                ))
+            })
+            .collect::<PyResult<Vec<(Node, Size<AvailableSpace>)>>>()?;
+        roots
             .into_iter()
-            .map(|e| NonRepeatedTrackSizingFunction::from(e))
-            .collect(),
-        grid_auto_columns: grid_auto_columns
-            .into_iter()
-            .map(|e| NonRepeatedTrackSizingFunction::from(e))
-            .collect(),
-        grid_auto_flow: GridAutoFlow::from_index(grid_auto_flow),
-        // Grid child properties
-        grid_row: Line::from(grid_row),
-        grid_column: Line::from(grid_column),
-        ..Default::default()
-    };
-    Box::into_raw(Box::new(style)) as usize
+            .map(|(node, available_space)| {
+                match tree.compute_layout(py, node, available_space, strict) {
+                    Ok(()) => Ok(true),
+                    Err(err) => {
+                        error!(target: "stretchable.taffylib", "Error computing the layout of node {}: {}", node_to_id(node), err);
+                        Ok(false)
+                    }
+                }
+            })
+            .collect()
+    })
 }
 
-// NODES
-
+// Same as `node_compute_layout`, except that it returns whether the location
+// or size of the node or any of its descendants changed.
 #[pyfunction]
-fn node_create(taffy_ptr: usize, style_ptr: usize) -> usize {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let style = unsafe { Box::from_raw(style_ptr as *mut Style) };
-    let node = taffy.new_leaf(*style.clone()).unwrap();
+fn node_compute_layout_diff(
+    py: Python,
+    taffy_ptr: u64,
+    node_id: u64,
+    available_space: PySize,
+) -> PyResult<bool> {
+    with_tree(taffy_ptr, |tree| {
+        let nodes = tree.subtree(lookup(tree, node_id)?);
+        let boxes = |tree: &Tree| -> Vec<(Point<f32>, Size<f32>)> {
+            nodes
+                .iter()
+                .map(|node| {
+                    let layout = tree.layout(*node);
+                    (layout.location, layout.size)
+                })
+                .collect()
+        };
+        let before = boxes(tree);
+        tree.compute_layout(
+            py,
+            nodes[0],
+            size_field("available_space", available_space)?,
+            false,
+        )?;
+        Ok(boxes(tree) != before)
+    })
+}
 
-    Box::leak(style);
-    Box::leak(taffy);
+#[derive(FromPyObject, IntoPyObject, PartialEq)]
+pub struct PyLayout {
+    order: i64,
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+}
 
-    Box::into_raw(Box::new(node)) as usize
+impl From<Layout> for PyLayout {
+    fn from(layout: Layout) -> Self {
+        PyLayout {
+            order: layout.order as i64,
+            left: layout.location.x,
+            top: layout.location.y,
+            width: layout.size.width,
+            height: layout.size.height,
+        }
+    }
 }
 
 #[pyfunction]
-unsafe fn node_add_child(taffy_ptr: usize, node_ptr: usize, child_ptr: usize) {
-    let mut taffy = Box::from_raw(taffy_ptr as *mut Taffy);
-    let node = Box::from_raw(node_ptr as *mut Node);
-    let child = Box::from_raw(child_ptr as *mut Node);
+fn node_get_layout(taffy_ptr: u64, node_id: u64) -> PyResult<PyLayout> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(PyLayout::from(tree.layout(lookup(tree, node_id)?)))
+    })
+}
 
-    taffy.add_child(*node, *child).unwrap();
+// Returns the (width, height) of the layout, without creating a `PyLayout` dict
+#[pyfunction]
+fn node_get_size(taffy_ptr: u64, node_id: u64) -> PyResult<(f32, f32)> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let size = tree.layout(lookup(tree, node_id)?).size;
+        Ok((size.width, size.height))
+    })
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
-    Box::leak(child);
+// Returns the (left, top) of the layout, without creating a `PyLayout` dict
+#[pyfunction]
+fn node_get_location(taffy_ptr: u64, node_id: u64) -> PyResult<(f32, f32)> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let location = tree.layout(lookup(tree, node_id)?).location;
+        Ok((location.x, location.y))
+    })
 }
 
+// Returns the order of the node among its siblings from the layout. This is the
+// index of the child for flexbox containers, while grid containers order
+// absolutely positioned children after the children in the flow. Painting and
+// hit testing also put absolutely positioned children after the others for
+// flexbox, see `Tree::paint_order`.
 #[pyfunction]
-fn node_drop(taffy_ptr: usize, node_ptr: usize) {
-    // Remove a specific node from the tree and drop it
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
+fn node_get_order(taffy_ptr: u64, node_id: u64) -> PyResult<i64> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.layout(lookup(tree, node_id)?).order as i64)
+    })
+}
 
-    _ = taffy.remove(*node);
-    Box::leak(taffy);
+// Returns whether any of the children of the node extend beyond its box, along
+// the x and y axis respectively. Taffy 0.3 does not report the content size of
+// a node, so this only considers the boxes of the children, not their contents.
+#[pyfunction]
+fn node_is_overflowing(taffy_ptr: u64, node_id: u64) -> PyResult<(bool, bool)> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let size = tree.layout(node).size;
+        let mut overflow = (false, false);
+        for child in tree.taffy.children(node).unwrap() {
+            let layout = tree.layout(child);
+            let (x, y) = (layout.location.x, layout.location.y);
+            overflow.0 |= x < 0. || x + layout.size.width > size.width;
+            overflow.1 |= y < 0. || y + layout.size.height > size.height;
+        }
+        Ok(overflow)
+    })
 }
 
+// Returns the width and height of the area covered by the node and the boxes of
+// all its descendants (eg. children positioned beyond its box), measured from
+// the top left corner of the node, ie. the scrollable area of the node. The
+// scroll padding of the node (see `node_set_scroll_padding`) is added around
+// the descendants.
 #[pyfunction]
-fn node_drop_all(taffy_ptr: usize) {
-    // Drops all nodes in the tree
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
+fn node_scroll_extent(taffy_ptr: u64, node_id: u64) -> PyResult<(f32, f32)> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let size = tree.layout(node).size;
+        let padding = tree.scroll_padding(node);
+        let mut extent = (size.width, size.height);
+        let mut stack = vec![(node, padding.left, padding.top)];
+        while let Some((parent, x, y)) = stack.pop() {
+            for child in tree.taffy.children(parent).unwrap() {
+                let layout = tree.layout(child);
+                let (x, y) = (x + layout.location.x, y + layout.location.y);
+                extent.0 = extent.0.max(x + layout.size.width + padding.right);
+                extent.1 = extent.1.max(y + layout.size.height + padding.bottom);
+                stack.push((child, x, y));
+            }
+        }
+        Ok(extent)
+    })
+}
 
-    taffy.clear();
-    Box::leak(taffy);
+// Returns the available space passed when the layout of the node was last
+// computed with the node as the root, eg. to see what percentages resolved
+// against.
+#[pyfunction]
+fn node_last_available_space(taffy_ptr: u64, node_id: u64) -> PyResult<PySize> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        match tree.available_spaces.get(&node) {
+            Some(available_space) => Ok(PySize::from(*available_space)),
+            None => Err(PyValueError::new_err(format!(
+                "the layout of node {} has not been computed",
+                node_id
+            ))),
+        }
+    })
 }
 
 #[pyfunction]
-fn node_replace_child_at_index(taffy_ptr: usize, node_ptr: usize, index: usize, child_ptr: usize) {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
-    let child = unsafe { Box::from_raw(child_ptr as *mut Node) };
+fn node_print_tree(taffy_ptr: u64, node_id: u64) -> PyResult<String> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let mut output = String::from("TREE\n");
+        tree.print_node(&mut output, lookup(tree, node_id)?, false, "");
+        Ok(output)
+    })
+}
 
-    taffy.replace_child_at_index(*node, index, *child).unwrap();
+// `key` is left out of the dict if the node has no key
+#[derive(FromPyObject)]
+pub struct PyTreeNode {
+    style: PyStyle,
+    key: Option<String>,
+    children: Vec<PyTreeNode>,
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
-    Box::leak(child);
+impl IntoPy<PyObject> for PyTreeNode {
+    fn into_py(self, py: Python) -> PyObject {
+        let dict = PyDict::new(py);
+        dict.set_item("style", self.style.into_py(py)).unwrap();
+        if let Some(key) = self.key {
+            dict.set_item("key", key).unwrap();
+        }
+        dict.set_item("children", self.children.into_py(py))
+            .unwrap();
+        dict.into()
+    }
 }
 
-#[pyfunction]
-fn node_remove_child(taffy_ptr: usize, node_ptr: usize, child_ptr: usize) {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
-    let child = unsafe { Box::from_raw(child_ptr as *mut Node) };
+// The styles of a `PyTreeNode` converted up front, so that no nodes are created
+// if any of the styles is invalid.
+struct StyleTree {
+    style: Style,
+    extras: StyleExtras,
+    key: Option<String>,
+    children: Vec<StyleTree>,
+}
 
-    // TODO: this fails with an unknown error...
-    taffy.remove_child(*node, *child).unwrap();
+impl TryFrom<PyTreeNode> for StyleTree {
+    type Error = PyErr;
+
+    fn try_from(node: PyTreeNode) -> PyResult<Self> {
+        let (style, extras) = style_from_py(node.style)?;
+        Ok(StyleTree {
+            style,
+            extras,
+            key: node.key,
+            children: node
+                .children
+                .into_iter()
+                .map(StyleTree::try_from)
+                .collect::<PyResult<_>>()?,
+        })
+    }
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
-    Box::leak(child);
+// Lengths without a value (eg. auto) have `NaN` as the value, which is not valid
+// JSON, so the value is written as `null` instead (and read back as `NaN`).
+fn json_length_values(py: Python, value: &PyAny, to_json: bool) -> PyResult<()> {
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        for (key, item) in dict.items().extract::<Vec<(&PyAny, &PyAny)>>()? {
+            if key.extract::<&str>().is_ok_and(|key| key == "value") {
+                if to_json && item.extract::<f32>().is_ok_and(f32::is_nan) {
+                    dict.set_item(key, py.None())?;
+                } else if !to_json && item.is_none() {
+                    dict.set_item(key, f32::NAN)?;
+                }
+            } else {
+                json_length_values(py, item, to_json)?;
+            }
+        }
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        for item in list {
+            json_length_values(py, item, to_json)?;
+        }
+    }
+    Ok(())
 }
 
+// Returns the styles and children of the node and all its descendants as JSON.
+// The node need not be a root: its parent and siblings are left out, so that
+// `tree_from_json` creates it as a standalone tree (eg. to save a reusable part
+// of a larger tree). Lengths without a value have `null` as the value, see
+// `json_length_values`.
 #[pyfunction]
-fn node_remove_child_at_index(taffy_ptr: usize, node_ptr: usize, index: usize) {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
-
-    taffy.remove_child_at_index(*node, index).unwrap();
+fn tree_to_json(py: Python, taffy_ptr: u64, root_id: u64) -> PyResult<String> {
+    let root = with_tree_ref(taffy_ptr, |tree| Ok(tree.tree_node(lookup(tree, root_id)?)))?;
+    let root = root.into_py(py);
+    json_length_values(py, root.as_ref(py), true)?;
+    let kwargs = [("sort_keys", true), ("allow_nan", false)].into_py_dict(py);
+    py.import("json")?
+        .call_method("dumps", (root,), Some(kwargs))?
+        .extract()
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
+// Creates the nodes from JSON produced by `tree_to_json` and returns the id of
+// the root node.
+#[pyfunction]
+fn tree_from_json(py: Python, taffy_ptr: u64, json: &str) -> PyResult<u64> {
+    let root = py.import("json")?.call_method1("loads", (json,))?;
+    json_length_values(py, root, false)?;
+    let root: PyTreeNode = root
+        .extract()
+        .map_err(|err| PyValueError::new_err(format!("invalid tree: {}", err)))?;
+    with_tree(taffy_ptr, |tree| {
+        let root = tree
+            .create_subtree(StyleTree::try_from(root)?)
+            .map_err(taffy_error)?;
+        Ok(node_to_id(root))
+    })
 }
 
+// Returns the layouts of the node and all its descendants (depth-first, parents
+// before their children), paired with the node ids.
 #[pyfunction]
-fn node_dirty(taffy_ptr: usize, node_ptr: usize) -> bool {
-    let taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
-    let dirty = taffy.dirty(*node).unwrap();
+fn subtree_get_layouts(taffy_ptr: u64, root_id: u64) -> PyResult<Vec<(u64, PyLayout)>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let nodes = tree.subtree(lookup(tree, root_id)?);
+        Ok(nodes
+            .into_iter()
+            .map(|node| (node_to_id(node), PyLayout::from(tree.layout(node))))
+            .collect())
+    })
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
+// Compares the layouts of the node and all its descendants to `previous` (eg.
+// from `subtree_get_layouts`), returning the nodes with a changed layout with
+// their previous and current layouts, in the order of `subtree_get_layouts`.
+// Nodes without a previous layout (eg. added since) are not included.
+#[pyfunction]
+fn layout_diff(
+    taffy_ptr: u64,
+    root_id: u64,
+    previous: Vec<(u64, PyLayout)>,
+) -> PyResult<Vec<(u64, PyLayout, PyLayout)>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let nodes = tree.subtree(lookup(tree, root_id)?);
+        let mut previous: HashMap<u64, PyLayout> = previous.into_iter().collect();
+        Ok(nodes
+            .into_iter()
+            .filter_map(|node| {
+                let node_id = node_to_id(node);
+                let before = previous.remove(&node_id)?;
+                let after = PyLayout::from(tree.layout(node));
+                (after != before).then_some((node_id, before, after))
+            })
+            .collect())
+    })
+}
 
-    dirty
+pub struct PyLayoutNode {
+    id: u64,
+    layout: PyLayout,
+    key: Option<String>,
+    children: Vec<PyLayoutNode>,
 }
-#[pyfunction]
-fn node_mark_dirty(taffy_ptr: usize, node_ptr: usize) {
-    let mut taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
 
-    taffy.mark_dirty(*node).unwrap();
+// The key is omitted for nodes without a key, as for `PyTreeNode`
+impl IntoPy<PyObject> for PyLayoutNode {
+    fn into_py(self, py: Python) -> PyObject {
+        let dict = PyDict::new(py);
+        dict.set_item("id", self.id).unwrap();
+        dict.set_item("layout", self.layout.into_py(py)).unwrap();
+        if let Some(key) = self.key {
+            dict.set_item("key", key).unwrap();
+        }
+        dict.set_item("children", self.children.into_py(py))
+            .unwrap();
+        dict.into()
+    }
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
+// Same as `subtree_get_layouts`, but nested: returns the id, layout and key (if
+// any, see `node_set_key`) of the node with the layouts of its children
+// (recursively).
+#[pyfunction]
+fn node_get_layout_tree(taffy_ptr: u64, root_id: u64) -> PyResult<PyLayoutNode> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(tree.layout_node(lookup(tree, root_id)?))
+    })
 }
 
+// Returns the layouts of the direct children of the node (in order), paired
+// with the node ids
 #[pyfunction]
-unsafe fn node_set_style(taffy: i64, node: i64, style: i64) {
-    let mut taffy = Box::from_raw(taffy as *mut Taffy);
-    let node = Box::from_raw(node as *mut Node);
-    let style = Box::from_raw(style as *mut Style);
+fn node_children_layouts(taffy_ptr: u64, node_id: u64) -> PyResult<Vec<(u64, PyLayout)>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let children = tree.taffy.children(lookup(tree, node_id)?).unwrap();
+        Ok(children
+            .into_iter()
+            .map(|child| (node_to_id(child), PyLayout::from(tree.layout(child))))
+            .collect())
+    })
+}
+
+// The edges of the box model, in the same order as `Edge` in Python
+enum BoxEdge {
+    Content,
+    Padding,
+    Border,
+    Margin,
+}
 
-    taffy.set_style(*node, *style).unwrap();
+impl TryFromIndex for BoxEdge {
+    fn try_from_index(index: i32) -> Result<BoxEdge, InvalidIndex> {
+        Ok(match index {
+            0 => BoxEdge::Content,
+            1 => BoxEdge::Padding,
+            2 => BoxEdge::Border,
+            3 => BoxEdge::Margin,
+            _ => return Err(InvalidIndex(index)),
+        })
+    }
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
-    // Box::leak(style);
+// Resolves margin, border or padding the same way as taffy, where percentages
+// are relative to the width of the content box of the parent.
+fn resolve_edges(
+    edges: Rect<LengthPercentageAuto>,
+    parent_width: Option<f32>,
+) -> PyResult<Rect<f32>> {
+    let resolve = |length| match (length, parent_width) {
+        (LengthPercentageAuto::Points(value), _) => Ok(value),
+        (LengthPercentageAuto::Percent(value), Some(width)) => Ok(value * width),
+        (LengthPercentageAuto::Percent(_), None) => Err(PyValueError::new_err(
+            "percentages cannot be resolved for a node without a parent",
+        )),
+        (LengthPercentageAuto::Auto, _) => Err(PyValueError::new_err(
+            "the box cannot be determined for auto margins",
+        )),
+    };
+    Ok(Rect {
+        left: resolve(edges.left)?,
+        right: resolve(edges.right)?,
+        top: resolve(edges.top)?,
+        bottom: resolve(edges.bottom)?,
+    })
 }
 
+// Returns the box (x, y, width, height) for the given edge of the box model,
+// relative to the parent, by expanding or contracting the layout (which is the
+// border box) with the margin, border and padding of the node.
 #[pyfunction]
-fn node_compute_layout(taffy: usize, node: usize, available_space: PySize) -> bool {
-    let mut taffy = unsafe { Box::from_raw(taffy as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node as *mut Node) };
-
-    let result = taffy.compute_layout(*node, Size::from(available_space));
+fn node_get_box(taffy_ptr: u64, node_id: u64, edge: i32) -> PyResult<Vec<f32>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        let layout = tree.layout(node);
+        let style = tree.directed_style(tree.taffy.style(node).unwrap().clone());
+        let parent_width = tree.parent_content_width(node);
+        let border = style.border.map(LengthPercentageAuto::from);
+        let padding = style.padding.map(LengthPercentageAuto::from);
+        let offsets = match field_from_index("edge", edge)? {
+            BoxEdge::Content => vec![(border, -1.), (padding, -1.)],
+            BoxEdge::Padding => vec![(border, -1.)],
+            BoxEdge::Border => vec![],
+            BoxEdge::Margin => vec![(style.margin, 1.)],
+        };
+        let (mut x, mut y) = (layout.location.x, layout.location.y);
+        let (mut width, mut height) = (layout.size.width, layout.size.height);
+        for (edges, factor) in offsets {
+            let edges = resolve_edges(edges, parent_width)?;
+            x -= factor * edges.left;
+            y -= factor * edges.top;
+            width += factor * (edges.left + edges.right);
+            height += factor * (edges.top + edges.bottom);
+        }
+        Ok(vec![x, y, width, height])
+    })
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
+// Returns the layout of the node with the location relative to the root of the
+// tree, rather than to the parent. If the layout is rounded, the absolute
+// location is rounded from the unrounded location (the same way as the size),
+// so that adjacent nodes line up.
+#[pyfunction]
+fn node_get_absolute_layout(taffy_ptr: u64, node_id: u64) -> PyResult<PyLayout> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(PyLayout::from(tree.absolute_layout(lookup(tree, node_id)?)))
+    })
+}
 
-    result.is_ok()
+// Sets the scroll offset of the node, which moves the absolute locations of its
+// descendants (see `node_get_absolute_layout`) up and left by `x` and `y`. The
+// scroll offset is kept when the layout is computed again, and does not change
+// the (relative) layouts computed by taffy.
+#[pyfunction]
+fn node_set_scroll_offset(taffy_ptr: u64, node_id: u64, x: f32, y: f32) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        if !(x.is_finite() && y.is_finite()) {
+            return Err(PyValueError::new_err(format!(
+                "scroll offset must be finite, not ({}, {})",
+                x, y
+            )));
+        }
+        if x == 0. && y == 0. {
+            tree.scroll_offsets.remove(&node);
+        } else {
+            tree.scroll_offsets.insert(node, Point { x, y });
+        }
+        Ok(())
+    })
 }
 
-#[derive(FromPyObject, IntoPyObject)]
-pub struct PyLayout {
-    order: i64,
-    left: f32,
-    top: f32,
-    width: f32,
-    height: f32,
+#[pyfunction]
+fn node_get_scroll_offset(taffy_ptr: u64, node_id: u64) -> PyResult<(f32, f32)> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        Ok(tree
+            .scroll_offsets
+            .get(&node)
+            .map_or((0., 0.), |offset| (offset.x, offset.y)))
+    })
 }
 
-impl From<Layout> for PyLayout {
-    fn from(layout: Layout) -> Self {
-        PyLayout {
-            order: layout.order as i64,
-            left: layout.location.x,
-            top: layout.location.y,
-            width: layout.size.width,
-            height: layout.size.height,
+// Sets the scroll padding of the node, which is kept clear around its descendants
+// when scrolled, similar to `scroll-padding` in CSS. Taffy does not model this,
+// so it is approximated: the absolute locations of the descendants (see
+// `node_get_absolute_layout`) are moved right and down by the left and top
+// padding, and the scroll extent (see `node_scroll_extent`) grows by the padding
+// on both sides. The (relative) layouts computed by taffy do not change.
+// Percentages are of the size of the node, and `None` removes the padding.
+#[pyfunction]
+fn node_set_scroll_padding(taffy_ptr: u64, node_id: u64, padding: Option<PyRect>) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        match padding {
+            Some(padding) => {
+                let padding: Rect<LengthPercentage> = rect_field("padding", padding)?;
+                let sides = [padding.left, padding.right, padding.top, padding.bottom];
+                if sides.iter().any(|side| match side {
+                    LengthPercentage::Points(value) | LengthPercentage::Percent(value) => {
+                        !(value.is_finite() && *value >= 0.)
+                    }
+                }) {
+                    return Err(PyValueError::new_err(
+                        "scroll padding must be finite and not negative",
+                    ));
+                }
+                tree.scroll_paddings.insert(node, padding);
+            }
+            None => {
+                tree.scroll_paddings.remove(&node);
+            }
         }
-    }
+        Ok(())
+    })
 }
 
 #[pyfunction]
-fn node_get_layout(taffy_ptr: usize, node_ptr: usize) -> PyLayout {
-    let taffy = unsafe { Box::from_raw(taffy_ptr as *mut Taffy) };
-    let node = unsafe { Box::from_raw(node_ptr as *mut Node) };
-    let layout = PyLayout::from(*taffy.layout(*node).unwrap());
+fn node_get_scroll_padding(taffy_ptr: u64, node_id: u64) -> PyResult<Option<PyRect>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        Ok(tree
+            .scroll_paddings
+            .get(&node)
+            .map(|padding| PyRect::from(*padding)))
+    })
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
+// Returns the deepest node within the subtree of `root_id` that contains the
+// point, with `x` and `y` relative to the root of the tree (same as
+// `node_get_absolute_layout`). Children are only considered if the point is
+// within their parent, and later children in the paint order (see
+// `Tree::paint_order`) are above earlier ones.
+#[pyfunction]
+fn node_at_point(taffy_ptr: u64, root_id: u64, x: f32, y: f32) -> PyResult<Option<u64>> {
+    with_tree_ref(taffy_ptr, |tree| {
+        let contains = |node: Node| {
+            let layout = tree.absolute_layout(node);
+            let (left, top) = (layout.location.x, layout.location.y);
+            x >= left && x < left + layout.size.width && y >= top && y < top + layout.size.height
+        };
+        let mut node = lookup(tree, root_id)?;
+        if !contains(node) {
+            return Ok(None);
+        }
+        loop {
+            let children = tree.paint_order(node);
+            match children.into_iter().rev().find(|child| contains(*child)) {
+                Some(child) => node = child,
+                None => return Ok(Some(node_to_id(node))),
+            }
+        }
+    })
+}
 
-    layout
+// Returns the exact layout of the node. If the layout was computed with rounding
+// enabled, this may differ from `node_get_layout`, otherwise they are the same.
+#[pyfunction]
+fn node_get_unrounded_layout(taffy_ptr: u64, node_id: u64) -> PyResult<PyLayout> {
+    with_tree_ref(taffy_ptr, |tree| {
+        Ok(PyLayout::from(
+            tree.unrounded_layout(lookup(tree, node_id)?),
+        ))
+    })
 }
 
 // create_exception!(
@@ -677,33 +3387,114 @@ fn node_get_layout(taffy_ptr: usize, node_ptr: usize) -> PyLayout {
 //     "Raised when the `measure` method assigned to a node failed."
 // );
 
+thread_local! {
+    // `None` unless computing a layout in strict mode, in which case it holds
+    // the first error raised by a measure function (if any).
+    static MEASURE_ERROR: RefCell<Option<Option<PyErr>>> = const { RefCell::new(None) };
+}
+
+// Incremented for every layout computation, to invalidate cached measurements
+static MEASURE_PASS: AtomicU64 = AtomicU64::new(0);
+
+// The `measure` function must return a tuple or list of exactly two floats
+// (width, height), which can each be NAN (or None) but not infinite. For an
+// axis with a known dimension, NAN means that the known dimension is used.
+fn measured_size(result: &PyAny) -> PyResult<Vec<f32>> {
+    let size = if result.is_instance_of::<PyTuple>() || result.is_instance_of::<PyList>() {
+        result.extract::<Vec<Option<f32>>>().ok().map(|size| {
+            size.into_iter()
+                .map(|v| v.unwrap_or(f32::NAN))
+                .collect::<Vec<_>>()
+        })
+    } else {
+        None
+    };
+    match size {
+        Some(size) if size.len() == 2 && size.iter().all(|v| !v.is_infinite()) => Ok(size),
+        _ => Err(PyTypeError::new_err(format!(
+            "`measure` must return a tuple or list of two floats (width, height), got {}",
+            result.repr()?
+        ))),
+    }
+}
+
+// The measure pass and the results for each set of (bitwise) arguments
+type MeasureCache = (u64, HashMap<[u32; 6], Size<f32>>);
+
 trait FromPyMeasure<T> {
-    fn from_py(node: PyObject, measure: PyObject) -> T;
+    fn from_py(context: PyObject, measure: PyObject) -> T;
 }
 
 impl FromPyMeasure<MeasureFunc> for MeasureFunc {
-    fn from_py(node: PyObject, measure: PyObject) -> MeasureFunc {
+    fn from_py(context: PyObject, measure: PyObject) -> MeasureFunc {
+        // Taffy can measure a node several times with the same arguments while
+        // computing a layout, so the results are cached for the computation
+        let cache: Mutex<MeasureCache> = Mutex::new((0, HashMap::new()));
         MeasureFunc::Boxed(Box::new(
             move |known_dimensions: Size<Option<f32>>,
                   available_space: Size<AvailableSpace>|
                   -> Size<f32> {
+                // In strict mode, skip the remaining calls after an error
+                if MEASURE_ERROR.with(|error| matches!(*error.borrow(), Some(Some(_)))) {
+                    return Size {
+                        width: f32::NAN,
+                        height: f32::NAN,
+                    };
+                }
+                let available_width: PyLength = available_space.width.into();
+                let available_height: PyLength = available_space.height.into();
+                let key = [
+                    known_dimensions.width.unwrap_or(f32::NAN).to_bits(),
+                    known_dimensions.height.unwrap_or(f32::NAN).to_bits(),
+                    available_width.dim as u32,
+                    available_width.value.to_bits(),
+                    available_height.dim as u32,
+                    available_height.value.to_bits(),
+                ];
+                let pass = MEASURE_PASS.load(Ordering::Relaxed);
+                {
+                    let mut cache = cache.lock().unwrap();
+                    if cache.0 != pass {
+                        *cache = (pass, HashMap::new());
+                    } else if let Some(size) = cache.1.get(&key) {
+                        return *size;
+                    }
+                }
                 // acquire lock
-                let size = Python::with_gil(|py| -> Vec<f32> {
+                let size = Python::with_gil(|py| -> Option<Size<f32>> {
                     // call function
-                    let available_width: PyLength = available_space.width.into();
-                    let available_height: PyLength = available_space.height.into();
                     let args = (
-                        &node,
+                        &context,
                         known_dimensions.width.unwrap_or(f32::NAN),
                         known_dimensions.height.unwrap_or(f32::NAN),
                         available_width,
                         available_height,
                     );
-                    let result = measure.call1(py, args);
+                    let result = measure
+                        .call1(py, args)
+                        .and_then(|result| measured_size(result.as_ref(py)));
 
                     match result {
-                        Ok(result) => result.extract(py).unwrap(),
+                        Ok(size) => {
+                            let or_known = |value: f32, known: Option<f32>| match known {
+                                Some(known) if value.is_nan() => known,
+                                _ => value,
+                            };
+                            Some(Size {
+                                width: or_known(size[0], known_dimensions.width),
+                                height: or_known(size[1], known_dimensions.height),
+                            })
+                        }
                         Err(err) => {
+                            // In strict mode, keep the error to raise it after computing
+                            let err =
+                                MEASURE_ERROR.with(|error| match &mut *error.borrow_mut() {
+                                    Some(slot @ None) => {
+                                        *slot = Some(err);
+                                        None
+                                    }
+                                    _ => Some(err),
+                                })?;
                             let traceback = match err.traceback(py) {
                                 Some(value) => match value.format() {
                                     Ok(tb) => format!("{}\n", tb),
@@ -712,48 +3503,105 @@ impl FromPyMeasure<MeasureFunc> for MeasureFunc {
                                 None => String::new(),
                             };
                             error!(target: "stretchable.taffylib", "Error in node `measure` (used `NAN, NAN` in place):\n{}{}", traceback, err);
-                            vec![f32::NAN, f32::NAN]
+                            None
                         }
                     }
                 });
 
-                // return result
-                Size {
-                    width: size[0],
-                    height: size[1],
+                // return result, errors are not cached
+                match size {
+                    Some(size) => {
+                        cache.lock().unwrap().1.insert(key, size);
+                        size
+                    }
+                    None => Size {
+                        width: f32::NAN,
+                        height: f32::NAN,
+                    },
                 }
             },
         ))
     }
 }
 
+// `context` can be any object (such as the Python node, or the text to be
+// measured) and is passed to `measure` as the first argument:
+//   measure(context, known_width, known_height, available_width, available_height)
+// The references to `context` and `measure` are released when the measure
+// function is removed or replaced, or the node is dropped.
+// Returns whether the node had a measure function before.
 #[pyfunction]
-unsafe fn node_set_measure(
-    taffy: i64,
-    node: i64,
-    node_self: PyObject,
-    measure: PyObject, // fn(i64, f32, f32) -> StretchSize
-) {
-    let mut taffy = Box::from_raw(taffy as *mut Taffy);
-    let node = Box::from_raw(node as *mut Node);
-
-    taffy
-        .set_measure(*node, Some(MeasureFunc::from_py(node_self, measure)))
-        .unwrap();
-
-    Box::leak(taffy);
-    Box::leak(node);
+fn node_set_measure(
+    taffy_ptr: u64,
+    node_id: u64,
+    context: PyObject,
+    measure: PyObject,
+) -> PyResult<bool> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        tree.taffy
+            .set_measure(node, Some(MeasureFunc::from_py(context, measure)))
+            .map_err(taffy_error)?;
+        Ok(!tree.measured.insert(node))
+    })
 }
 
+// Returns whether the node had a measure function before.
 #[pyfunction]
-unsafe fn node_remove_measure(taffy: i64, node: i64) {
-    let mut taffy = Box::from_raw(taffy as *mut Taffy);
-    let node = Box::from_raw(node as *mut Node);
-
-    taffy.set_measure(*node, None).unwrap();
+fn node_remove_measure(taffy_ptr: u64, node_id: u64) -> PyResult<bool> {
+    with_tree(taffy_ptr, |tree| {
+        let node = lookup(tree, node_id)?;
+        tree.taffy.set_measure(node, None).map_err(taffy_error)?;
+        Ok(tree.measured.remove(&node))
+    })
+}
 
-    Box::leak(taffy);
-    Box::leak(node);
+// Same as `node_set_measure` for many nodes in a single call, eg. for the text
+// leaves of a document. The nodes share the same `measure` function, with the
+// context of each node taken from `contexts` (or `None` when not given). With
+// `measure` set to `None`, the measure functions of the nodes are removed.
+#[pyfunction]
+#[pyo3(signature = (taffy_ptr, node_ids, measure=None, contexts=None))]
+fn nodes_set_measure(
+    py: Python,
+    taffy_ptr: u64,
+    node_ids: Vec<u64>,
+    measure: Option<PyObject>,
+    contexts: Option<Vec<PyObject>>,
+) -> PyResult<()> {
+    with_tree(taffy_ptr, |tree| {
+        // Look up all nodes first, so that no measure function is changed if
+        // any node is invalid
+        let nodes = node_ids
+            .into_iter()
+            .map(|node_id| lookup(tree, node_id))
+            .collect::<PyResult<Vec<Node>>>()?;
+        let contexts = match contexts {
+            Some(contexts) if contexts.len() != nodes.len() => {
+                return Err(PyValueError::new_err(format!(
+                    "expected {} contexts, got {}",
+                    nodes.len(),
+                    contexts.len()
+                )))
+            }
+            Some(contexts) => contexts,
+            None => vec![py.None(); nodes.len()],
+        };
+        for (node, context) in nodes.into_iter().zip(contexts) {
+            match &measure {
+                Some(measure) => {
+                    let measure = MeasureFunc::from_py(context, measure.clone_ref(py));
+                    tree.taffy.set_measure(node, Some(measure)).unwrap();
+                    tree.measured.insert(node);
+                }
+                None => {
+                    tree.taffy.set_measure(node, None).unwrap();
+                    tree.measured.remove(&node);
+                }
+            }
+        }
+        Ok(())
+    })
 }
 
 // MODULE
@@ -761,32 +3609,95 @@ unsafe fn node_remove_measure(taffy: i64, node: i64) {
 // for pyo3-pack, name must match module.
 #[pymodule]
 fn taffylib(py: Python, m: &PyModule) -> PyResult<()> {
-    Logger::new(py, Caching::LoggersAndLevels)?
+    let reset = Logger::new(py, Caching::LoggersAndLevels)?
         .filter(LevelFilter::Warn)
-        // .filter_target("stretchable::taffylib".to_owned(), LevelFilter::Warn)
+        // Debug is only used for profiling, see `set_profiling`
+        .filter_target("stretchable.taffylib".to_owned(), LevelFilter::Debug)
         .install()
         .unwrap();
+    _ = LOG_RESET.set(reset);
 
     m.add_wrapped(wrap_pyfunction!(init))?;
     m.add_wrapped(wrap_pyfunction!(free))?;
     m.add_wrapped(wrap_pyfunction!(enable_rounding))?;
+    m.add_wrapped(wrap_pyfunction!(round_layout))?;
     m.add_wrapped(wrap_pyfunction!(disable_rounding))?;
-    m.add_wrapped(wrap_pyfunction!(style_create))?;
-    m.add_wrapped(wrap_pyfunction!(style_drop))?;
+    m.add_wrapped(wrap_pyfunction!(is_rounding_enabled))?;
+    m.add_wrapped(wrap_pyfunction!(set_rounding_scale))?;
+    m.add_wrapped(wrap_pyfunction!(set_direction))?;
+    m.add_wrapped(wrap_pyfunction!(set_profiling))?;
+    m.add_wrapped(wrap_pyfunction!(total_node_count))?;
+    m.add_wrapped(wrap_pyfunction!(tree_roots))?;
+    m.add_wrapped(wrap_pyfunction!(reserve))?;
     m.add_wrapped(wrap_pyfunction!(node_create))?;
+    m.add_wrapped(wrap_pyfunction!(node_create_default))?;
+    m.add_wrapped(wrap_pyfunction!(set_default_style))?;
+    m.add_wrapped(wrap_pyfunction!(nodes_create))?;
+    m.add_wrapped(wrap_pyfunction!(node_create_with_children))?;
+    m.add_wrapped(wrap_pyfunction!(node_clone_subtree))?;
     m.add_wrapped(wrap_pyfunction!(node_drop))?;
+    m.add_wrapped(wrap_pyfunction!(node_clear_children))?;
     m.add_wrapped(wrap_pyfunction!(node_drop_all))?;
+    m.add_wrapped(wrap_pyfunction!(node_exists))?;
     m.add_wrapped(wrap_pyfunction!(node_add_child))?;
+    m.add_wrapped(wrap_pyfunction!(node_set_children))?;
     m.add_wrapped(wrap_pyfunction!(node_replace_child_at_index))?;
+    m.add_wrapped(wrap_pyfunction!(node_insert_child_at_index))?;
+    m.add_wrapped(wrap_pyfunction!(node_swap_children))?;
     m.add_wrapped(wrap_pyfunction!(node_remove_child))?;
     m.add_wrapped(wrap_pyfunction!(node_remove_child_at_index))?;
+    m.add_wrapped(wrap_pyfunction!(node_detach))?;
+    m.add_wrapped(wrap_pyfunction!(node_replace_subtree))?;
+    m.add_wrapped(wrap_pyfunction!(node_reparent))?;
+    m.add_wrapped(wrap_pyfunction!(node_children))?;
+    m.add_wrapped(wrap_pyfunction!(node_child_count))?;
+    m.add_wrapped(wrap_pyfunction!(node_child_at_index))?;
+    m.add_wrapped(wrap_pyfunction!(node_parent))?;
+    m.add_wrapped(wrap_pyfunction!(node_is_root))?;
+    m.add_wrapped(wrap_pyfunction!(node_depth))?;
     m.add_wrapped(wrap_pyfunction!(node_dirty))?;
+    m.add_wrapped(wrap_pyfunction!(node_dirty_recursive))?;
     m.add_wrapped(wrap_pyfunction!(node_mark_dirty))?;
+    m.add_wrapped(wrap_pyfunction!(subtree_dirty_count))?;
+    m.add_wrapped(wrap_pyfunction!(subtree_leaf_count))?;
+    m.add_wrapped(wrap_pyfunction!(subtree_paint_order))?;
     m.add_wrapped(wrap_pyfunction!(node_set_style))?;
+    m.add_wrapped(wrap_pyfunction!(nodes_set_styles))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_style))?;
     m.add_wrapped(wrap_pyfunction!(node_get_layout))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_size))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_location))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_order))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_absolute_layout))?;
+    m.add_wrapped(wrap_pyfunction!(node_set_scroll_offset))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_scroll_offset))?;
+    m.add_wrapped(wrap_pyfunction!(node_set_scroll_padding))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_scroll_padding))?;
+    m.add_wrapped(wrap_pyfunction!(node_set_key))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_key))?;
+    m.add_wrapped(wrap_pyfunction!(find_node_by_key))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_box))?;
+    m.add_wrapped(wrap_pyfunction!(node_at_point))?;
+    m.add_wrapped(wrap_pyfunction!(subtree_get_layouts))?;
+    m.add_wrapped(wrap_pyfunction!(layout_diff))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_layout_tree))?;
+    m.add_wrapped(wrap_pyfunction!(node_children_layouts))?;
+    m.add_wrapped(wrap_pyfunction!(node_print_tree))?;
+    m.add_wrapped(wrap_pyfunction!(tree_to_json))?;
+    m.add_wrapped(wrap_pyfunction!(tree_from_json))?;
+    m.add_wrapped(wrap_pyfunction!(node_get_unrounded_layout))?;
+    m.add_wrapped(wrap_pyfunction!(node_is_overflowing))?;
+    m.add_wrapped(wrap_pyfunction!(node_scroll_extent))?;
+    m.add_wrapped(wrap_pyfunction!(node_last_available_space))?;
     m.add_wrapped(wrap_pyfunction!(node_set_measure))?;
     m.add_wrapped(wrap_pyfunction!(node_remove_measure))?;
+    m.add_wrapped(wrap_pyfunction!(nodes_set_measure))?;
     m.add_wrapped(wrap_pyfunction!(node_compute_layout))?;
+    m.add_wrapped(wrap_pyfunction!(node_compute_layout_if_dirty))?;
+    m.add_wrapped(wrap_pyfunction!(compute_layouts))?;
+    m.add_wrapped(wrap_pyfunction!(node_intrinsic_size))?;
+    m.add_wrapped(wrap_pyfunction!(node_measure))?;
+    m.add_wrapped(wrap_pyfunction!(node_compute_layout_diff))?;
     // m.add("NodeMeasureError", py.get_type::<NodeMeasureError>())?;
 
     Ok(())
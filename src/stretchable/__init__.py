@@ -8,7 +8,7 @@
 # from .node import Box, Layout, Node, reset
 # from .style import Rect, Size, Style
 
-from .node import Box, Edge, Node
+from .node import Box, Edge, Node, make_aspect_ratio_measure
 from .style import Style
 
 __all__ = [
@@ -16,6 +16,7 @@ __all__ = [
     "Edge",
     "Box",
     "Style",
+    "make_aspect_ratio_measure",
 ]
 
 
@@ -3,8 +3,10 @@ from __future__ import annotations
 import logging
 import re
 from enum import StrEnum, auto
-from typing import Callable, Iterable, Optional, Self, SupportsIndex
+from math import isnan
+from typing import Callable, Iterable, Optional, Self, Sequence, SupportsIndex
 from xml.etree import ElementTree
+from xml.sax.saxutils import escape, quoteattr
 
 import attrs
 from attrs import define
@@ -18,7 +20,14 @@ from .exceptions import (
     TaffyUnavailableError,
 )
 from .style import Display, Rect, Style
-from .style.geometry.length import AUTO, NAN, LengthAvailableSpace, Scale
+from .style.geometry.length import (
+    AUTO,
+    NAN,
+    AvailableSpace,
+    Length,
+    LengthAvailableSpace,
+    Scale,
+)
 from .style.geometry.size import SizeAvailableSpace, SizePoints, SizePointsPercentAuto
 
 logging.basicConfig(format="%(levelname)s:%(name)s:%(message)s")
@@ -29,6 +38,45 @@ _valid_key = re.compile(r"^[-_!:;()\]\[a-zA-Z0-9]*[a-zA-Z]+[-_!:;()\]\[a-zA-Z0-9
 
 MeasureFunc = Callable[[SizePoints, SizeAvailableSpace], SizePoints]
 
+
+def make_aspect_ratio_measure(
+    intrinsic_width: float, intrinsic_height: float
+) -> MeasureFunc:
+    """Returns a measure function for content with a fixed aspect ratio (such as
+    an image), for use with :py:attr:`Node.measure`.
+
+    If the width (or height) is known, the other dimension is computed from the
+    aspect ratio. Otherwise, the content fills the available width if it is
+    definite (or the available height, if only that is definite), and has the
+    intrinsic size when measured at max-content. As with images that scale down
+    to fit their container in CSS, the content has no size at min-content."""
+    if not (intrinsic_width > 0 and intrinsic_height > 0):
+        raise ValueError("The intrinsic width and height must be positive")
+    ratio = intrinsic_width / intrinsic_height
+
+    def measure(
+        known_dimensions: SizePoints, available_space: SizeAvailableSpace
+    ) -> SizePoints:
+        width, height = known_dimensions.width.value, known_dimensions.height.value
+        if isnan(width) and isnan(height):
+            available_width = available_space.width
+            available_height = available_space.height
+            if available_width.scale == AvailableSpace.DEFINITE:
+                width = available_width.value
+            elif available_height.scale == AvailableSpace.DEFINITE:
+                height = available_height.value
+            elif available_width.scale == AvailableSpace.MIN_CONTENT:
+                width = 0
+            else:
+                width = intrinsic_width
+        if isnan(height):
+            height = width / ratio
+        elif isnan(width):
+            width = height * ratio
+        return SizePoints(width, height)
+
+    return measure
+
 USE_ROOT_CONTAINER: bool = False
 
 
@@ -157,7 +205,7 @@ class Node(list["Node"]):
         self._zorder = None
         self._parent = None
         self._container: Node = None
-        self._measure = measure
+        self._measure = None
 
         # Style
         if not style:
@@ -167,14 +215,18 @@ class Node(list["Node"]):
         self._style = style
 
         # Create node in taffy
-        self.__ptr = taffylib.node_create(taffy._ptr, style._ptr)
+        self.__ptr = taffylib.node_create(taffy._ptr, style.to_dict())
         # taffy._nodes.add(self.__ptr)
         logger.debug(
             "node_create(taffy: %s, style: %s) -> %s",
             taffy._ptr,
-            style._ptr,
+            style,
             self.__ptr,
         )
+        if key is not None:
+            taffylib.node_set_key(taffy._ptr, self.__ptr, key)
+        if measure is not None:
+            self.measure = measure
 
         # Children
         self._children = []
@@ -461,7 +513,11 @@ class Node(list["Node"]):
 
     @property
     def measure(self) -> MeasureFunc:
-        """Method invoked to measure the node size during computation of layout."""
+        """Method invoked to measure the node size during computation of layout.
+
+        If a dimension is already known, the method can leave the corresponding
+        dimension of the returned size undefined (`None` or `NAN`), in which case
+        the known dimension is used."""
         return self._measure
 
     @measure.setter
@@ -483,7 +539,9 @@ class Node(list["Node"]):
 
     def compute_layout(
         self,
-        available_space: Optional[SizeAvailableSpace | tuple[float, float]] = None,
+        available_space: Optional[
+            SizeAvailableSpace | tuple[float | Length, float | Length] | Length
+        ] = None,
         *,
         use_rounding: bool = False,
     ) -> bool:
@@ -492,18 +550,29 @@ class Node(list["Node"]):
         Parameters
         ----------
         available_space
-            The available space for the layout. It may be provided as :py:obj:`SizeAvailableSpace`, as a :py:obj:`tuple` of width and height, or omitted
+            The available space for the layout. It may be provided as :py:obj:`SizeAvailableSpace`, as a :py:obj:`tuple` of width and height, as a single value used for both, or omitted
+            (in which case :py:obj:`MAX_CONTENT <stretchable.style.MAX_CONTENT>` is used). Besides points, each of the width and height may be
+            :py:obj:`MIN_CONTENT <stretchable.style.MIN_CONTENT>` or :py:obj:`MAX_CONTENT <stretchable.style.MAX_CONTENT>`, eg. to get the intrinsic
+            (unwrapped) width of the content with ``(MAX_CONTENT, 100)``.
         use_rounding
             If ``True``, all positions and dimensions will be rounded to integers.
 
         Returns
         -------
-        ``True`` if layout was computed successfully, ``False`` otherwise.
+        ``True`` if layout was computed successfully. If the computation fails,
+        an exception is raised.
 
         Notes
         -----
 
         Depending on the nodes, the resulting layout may extend outside ``available_space``.
+
+        Nodes are not marked dirty when only ``available_space`` changes (eg. when
+        resizing along one axis), so the sizes cached by taffy for unchanged nodes are
+        reused and :py:obj:`measure` is called less often. Taffy only keeps a few cached
+        sizes per node, and nodes measured with a different available space than before
+        are measured again. Nodes with ``calc`` lengths are marked dirty when the size
+        they are resolved against changes.
         """
 
         if not taffy._ptr:
@@ -511,8 +580,8 @@ class Node(list["Node"]):
 
         if not available_space:
             available_space = SizeAvailableSpace.default()
-        elif not isinstance(available_space, SizeAvailableSpace):
-            available_space = SizeAvailableSpace(*available_space)
+        else:
+            available_space = SizeAvailableSpace.from_any(available_space)
 
         if USE_ROOT_CONTAINER and self.is_root:
             # If this is a root node, use a container node to be able to get the
@@ -676,6 +745,196 @@ class Node(list["Node"]):
 
     # endregion
 
+    # region Export
+
+    def to_svg(
+        self,
+        *,
+        fill: str = "none",
+        stroke: str = "black",
+        stroke_width: float = 1,
+        depth_colors: Optional[Sequence[str]] = None,
+        keys: bool = False,
+    ) -> str:
+        """
+        Get an SVG image of the computed layout of this node and its descendants,
+        with a ``<rect>`` for the border box of each visible node.
+
+        Parameters
+        ----------
+        fill
+            The fill color of the rectangles
+        stroke
+            The stroke color of the rectangles
+        stroke_width
+            The stroke width of the rectangles
+        depth_colors
+            If provided, the rectangles are filled with the color at the index
+            corresponding to the depth of the node relative to this node (cycling
+            through the colors), instead of ``fill``
+        keys
+            If ``True``, the rectangles of nodes with a key are labeled with the
+            key
+
+        Returns
+        -------
+        The SVG image (with the size of this node) as a string
+        """
+
+        if self.is_dirty:
+            raise LayoutNotComputedError
+
+        origin = self.get_box(relative=False)
+        elements = []
+
+        def add(node: Node, depth: int) -> None:
+            if not node.is_visible:
+                return
+            box = node.get_box(relative=False)
+            x, y = box.x - origin.x, box.y - origin.y
+            color = depth_colors[depth % len(depth_colors)] if depth_colors else fill
+            elements.append(
+                f'<rect x="{x:g}" y="{y:g}" width="{box.width:g}" '
+                f'height="{box.height:g}" fill={quoteattr(color)} '
+                f'stroke={quoteattr(stroke)} stroke-width="{stroke_width:g}"/>'
+            )
+            if keys and node.key:
+                elements.append(
+                    f'<text x="{x:g}" y="{y:g}" dominant-baseline="hanging">'
+                    f"{escape(node.key)}</text>"
+                )
+            for child in node:
+                add(child, depth + 1)
+
+        add(self, 0)
+        return "\n".join(
+            [
+                '<svg xmlns="http://www.w3.org/2000/svg" '
+                f'width="{origin.width:g}" height="{origin.height:g}">',
+                *elements,
+                "</svg>",
+            ]
+        )
+
+    def to_html(
+        self,
+        *,
+        outline: str = "1px solid black",
+        depth_colors: Optional[Sequence[str]] = None,
+    ) -> str:
+        """
+        Get the computed layout of this node and its descendants as HTML, with an
+        absolutely positioned ``<div>`` for the border box of each visible node
+        (inside the ``<div>`` of this node). Nodes with a key get it as their
+        ``id`` attribute.
+
+        Parameters
+        ----------
+        outline
+            The CSS outline of the boxes
+        depth_colors
+            If provided, the boxes are filled with the color at the index
+            corresponding to the depth of the node relative to this node (cycling
+            through the colors). Otherwise, the boxes are tinted darker with depth.
+
+        Returns
+        -------
+        The HTML as a string
+        """
+
+        if self.is_dirty:
+            raise LayoutNotComputedError
+
+        origin = self.get_box(relative=False)
+        elements = []
+
+        def div(node: Node, depth: int, position: str) -> str:
+            box = node.get_box(relative=False)
+            if depth_colors:
+                color = depth_colors[depth % len(depth_colors)]
+            else:
+                color = f"hsl(210, 50%, {max(20, 95 - 10 * depth)}%)"
+            style = (
+                f"position: {position}; box-sizing: border-box; "
+                f"left: {box.x - origin.x:g}px; top: {box.y - origin.y:g}px; "
+                f"width: {box.width:g}px; height: {box.height:g}px; "
+                f"background: {color}; outline: {outline}"
+            )
+            id = f" id={quoteattr(node.key)}" if node.key else ""
+            return f"<div{id} style={quoteattr(style)}>"
+
+        def add(node: Node, depth: int) -> None:
+            if not node.is_visible:
+                return
+            elements.append(div(node, depth, "absolute") + "</div>")
+            for child in node:
+                add(child, depth + 1)
+
+        for child in self:
+            add(child, 1)
+        return "\n".join([div(self, 0, "relative"), *elements, "</div>"])
+
+    def to_ascii(
+        self, width: int = 80, height: Optional[int] = None, *, chars: str = ".:-=*#%@"
+    ) -> str:
+        """
+        Get the computed layout of this node and its descendants as ASCII art,
+        for quick debugging. The border box of each visible node is outlined and
+        filled with the character at the index corresponding to the depth of the
+        node relative to this node (cycling through ``chars``).
+
+        Parameters
+        ----------
+        width
+            The number of columns
+        height
+            The number of rows. If not provided, it is determined from the
+            aspect ratio of this node, assuming characters twice as tall as wide.
+        chars
+            The characters used to fill the nodes, by depth
+
+        Returns
+        -------
+        The rows of the image, separated by newlines
+        """
+
+        if self.is_dirty:
+            raise LayoutNotComputedError
+
+        origin = self.get_box(relative=False)
+        if origin.width <= 0 or origin.height <= 0:
+            raise ValueError("The node must have a non-zero size to be rendered")
+        scale_x = width / origin.width
+        if height is None:
+            height = max(1, round(origin.height * scale_x / 2))
+        scale_y = height / origin.height
+        grid = [[" "] * width for _ in range(height)]
+
+        def add(node: Node, depth: int) -> None:
+            if not node.is_visible:
+                return
+            box = node.get_box(relative=False)
+            x0 = max(0, round((box.x - origin.x) * scale_x))
+            x1 = min(width, round((box.x - origin.x + box.width) * scale_x))
+            y0 = max(0, round((box.y - origin.y) * scale_y))
+            y1 = min(height, round((box.y - origin.y + box.height) * scale_y))
+            for y in range(y0, y1):
+                for x in range(x0, x1):
+                    if y in (y0, y1 - 1):
+                        char = "+" if x in (x0, x1 - 1) else "-"
+                    elif x in (x0, x1 - 1):
+                        char = "|"
+                    else:
+                        char = chars[depth % len(chars)]
+                    grid[y][x] = char
+            for child in node:
+                add(child, depth + 1)
+
+        add(self, 0)
+        return "\n".join("".join(row) for row in grid)
+
+    # endregion
+
     @classmethod
     def from_xml(
         cls, xml: str, customize: Callable[[Self, ElementTree.Element], Self] = None
@@ -726,11 +985,11 @@ class Container:
         self._root = root
 
         # Create node in taffy
-        self.__ptr = taffylib.node_create(taffy._ptr, self._style._ptr)
+        self.__ptr = taffylib.node_create(taffy._ptr, self._style.to_dict())
         logger.debug(
             "node_create(taffy: %s, style: %s) -> %s",
             taffy._ptr,
-            self._style._ptr,
+            self._style,
             self.__ptr,
         )
         # Add root node as child of this node
@@ -802,4 +1061,4 @@ class Container:
             )
         )
         if self._ptr:
-            taffylib.node_set_style(taffy._ptr, self._ptr, self._style._ptr)
+            taffylib.node_set_style(taffy._ptr, self._ptr, self._style.to_dict())
@@ -2,10 +2,12 @@ from .core import (
     AlignContent,
     AlignItems,
     AlignSelf,
+    BoxSizing,
     Display,
     FlexDirection,
     FlexWrap,
     GridAutoFlow,
+    GridIndex,
     GridIndexType,
     GridPlacement,
     GridTrackSize,
@@ -17,7 +19,17 @@ from .core import (
     Position,
     Style,
 )
-from .geometry.length import AUTO, NAN, PCT, PT, Length
+from .geometry.length import (
+    AUTO,
+    FR,
+    MAX_CONTENT,
+    MIN_CONTENT,
+    NAN,
+    PCT,
+    PT,
+    Length,
+    calc,
+)
 from .geometry.rect import Rect
 from .geometry.size import Size
 
@@ -26,6 +38,7 @@ __all__ = [
     "Size",
     "Rect",
     "Position",
+    "BoxSizing",
     "Overflow",
     "Length",
     "JustifySelf",
@@ -33,6 +46,7 @@ __all__ = [
     "JustifyContent",
     "GridPlacement",
     "GridAutoFlow",
+    "GridIndex",
     "GridIndexType",
     "GridTrackSize",
     "GridTrackSizing",
@@ -44,6 +58,10 @@ __all__ = [
     "AlignContent",
     "PT",
     "PCT",
+    "FR",
+    "MIN_CONTENT",
+    "MAX_CONTENT",
     "NAN",
     "AUTO",
+    "calc",
 ]
@@ -5,19 +5,21 @@ import re
 from enum import Enum, IntEnum
 from typing import Any, Iterable, Optional
 
+import attrs
 from attrs import define, field, validators
 
-from .. import taffylib
 from .geometry import length, rect
 from .geometry import size as _size
 from .props import (
     AlignContent,
     AlignItems,
     AlignSelf,
+    BoxSizing,
     Display,
     FlexDirection,
     FlexWrap,
     GridAutoFlow,
+    GridIndex,
     GridIndexType,
     GridPlacement,
     GridTrackSize,
@@ -54,6 +56,44 @@ def grid_auto_from_any(value: Any) -> list[GridTrackSize]:
     return [GridTrackSize.from_any(v) for v in value]
 
 
+def grid_areas_from_any(value: Any) -> list[str]:
+    if value is None:
+        return []
+    if isinstance(value, str):
+        value = [value]
+    return [" ".join(row.split()) for row in value]
+
+
+def grid_area_lines(
+    areas: list[str],
+) -> tuple[dict[str, int], dict[str, int]]:
+    """Returns the row and column lines named by the grid areas, same as
+    ``grid-template-areas`` in CSS: each area ``<name>`` names the lines
+    ``<name>-start`` and ``<name>-end`` around the cells with that name (cells
+    named ``.`` are not part of any area)."""
+    cells: dict[str, list[tuple[int, int]]] = dict()
+    columns = None
+    for row, names in enumerate(areas):
+        names = names.split()
+        if columns is not None and len(names) != columns:
+            raise ValueError(
+                "All rows of grid_template_areas must have the same number of areas"
+            )
+        columns = len(names)
+        for column, name in enumerate(names):
+            if not re.fullmatch(r"\.+", name):
+                cells.setdefault(name, []).append((row, column))
+    rows, cols = dict(), dict()
+    for name, area in cells.items():
+        row_start, col_start = min(r for r, _ in area), min(c for _, c in area)
+        row_end, col_end = max(r for r, _ in area) + 1, max(c for _, c in area) + 1
+        if len(area) != (row_end - row_start) * (col_end - col_start):
+            raise ValueError(f"Grid area '{name}' is not a rectangle")
+        rows[f"{name}-start"], rows[f"{name}-end"] = row_start + 1, row_end + 1
+        cols[f"{name}-start"], cols[f"{name}-end"] = col_start + 1, col_end + 1
+    return rows, cols
+
+
 @define(frozen=True, kw_only=True)
 class Style:
     """Style configuration for a node.
@@ -136,14 +176,18 @@ class Style:
     )
 
     # Size
-    size: _size.SizePointsPercentAuto = field(
-        default=length.AUTO, converter=_size.SizePointsPercentAuto.from_any
+    box_sizing: BoxSizing = field(
+        default=BoxSizing.BORDER_BOX,
+        validator=[validators.instance_of(BoxSizing)],
     )
-    min_size: _size.SizePointsPercentAuto = field(
-        default=length.AUTO, converter=_size.SizePointsPercentAuto.from_any
+    size: _size.SizePointsPercentAutoCalc = field(
+        default=length.AUTO, converter=_size.SizePointsPercentAutoCalc.from_any
     )
-    max_size: _size.SizePointsPercentAuto = field(
-        default=length.AUTO, converter=_size.SizePointsPercentAuto.from_any
+    min_size: _size.SizePointsPercentAutoCalc = field(
+        default=length.AUTO, converter=_size.SizePointsPercentAutoCalc.from_any
+    )
+    max_size: _size.SizePointsPercentAutoCalc = field(
+        default=length.AUTO, converter=_size.SizePointsPercentAutoCalc.from_any
     )
     aspect_ratio: float = field(default=None)
 
@@ -179,6 +223,11 @@ class Style:
     grid_auto_columns: list[GridTrackSize] = field(
         default=None, converter=grid_auto_from_any
     )
+    grid_template_areas: list[str] = field(
+        default=None, converter=grid_areas_from_any
+    )
+    grid_template_row_names: dict[str, int] = field(factory=dict, converter=dict)
+    grid_template_column_names: dict[str, int] = field(factory=dict, converter=dict)
 
     # Grid child
     grid_row: GridPlacement = field(
@@ -188,65 +237,110 @@ class Style:
         factory=GridPlacement, converter=GridPlacement.from_any
     )
 
-    __ptr: int = field(init=False, default=None)
+    @box_sizing.validator
+    def _check_box_sizing(self, attribute, value) -> None:
+        if value != BoxSizing.CONTENT_BOX:
+            return
+        for edges in (self.padding, self.border):
+            for edge in (edges.top, edges.right, edges.bottom, edges.left):
+                if edge.scale == length.Scale.PERCENT:
+                    raise ValueError(
+                        "box_sizing CONTENT_BOX is not supported with percentage "
+                        "padding or border"
+                    )
+
+    def _border_box(
+        self, size: _size.SizePointsPercentAutoCalc
+    ) -> _size.SizePointsPercentAutoCalc:
+        # Taffy always uses the border box, so content box sizes are converted
+        # by adding the padding and border (calc for percentages)
+        if self.box_sizing == BoxSizing.BORDER_BOX:
+            return size
+
+        def add(value: length.LengthBase, edges: tuple[length.LengthBase]):
+            points = sum(edge.value for edge in edges)
+            match value.scale:
+                case length.Scale.POINTS:
+                    return value.value + points
+                case length.Scale.PERCENT if points:
+                    return length.calc(value, points)
+                case length.Scale.CALC:
+                    offset = value.offset + points
+                    return length.Length(value.scale, value.value, offset)
+            return value
+
+        padding, border = self.padding, self.border
+        return _size.SizePointsPercentAutoCalc(
+            add(size.width, (padding.left, padding.right, border.left, border.right)),
+            add(size.height, (padding.top, padding.bottom, border.top, border.bottom)),
+        )
 
-    def to_args(self) -> tuple:
-        return (
+    def with_content_basis(self, direction: FlexDirection = FlexDirection.ROW) -> Style:
+        """Returns a copy of the style with the flex basis sized by the content of
+        the node, similar to ``flex-basis: content`` in CSS (which is not supported
+        by taffy).
+
+        This is approximated by ``flex_basis=AUTO`` with an ``AUTO`` size along the
+        main axis (the width for ``ROW`` and the height for ``COLUMN``), since an
+        automatic flex basis is the size along the main axis, if set, and otherwise
+        the size of the content.
+
+        Parameters
+        ----------
+        direction
+            The ``flex_direction`` of the parent, which determines the main axis
+        """
+        size = self.size
+        if direction in (FlexDirection.ROW, FlexDirection.ROW_REVERSE):
+            size = _size.SizePointsPercentAutoCalc(length.AUTO, size.height)
+        else:
+            size = _size.SizePointsPercentAutoCalc(size.width, length.AUTO)
+        return attrs.evolve(self, flex_basis=length.AUTO, size=size)
+
+    def to_dict(self) -> dict:
+        row_names, column_names = grid_area_lines(self.grid_template_areas)
+        return dict(
             # Layout mode
-            self.display,
+            display=self.display,
             # Position
-            self.position,
-            self.inset.to_dict(),
+            position=self.position,
+            inset=self.inset.to_dict(),
             # Alignment
-            self.gap.to_dict(),
+            align_items=self.align_items,
+            justify_items=self.justify_items,
+            align_self=self.align_self,
+            justify_self=self.justify_self,
+            align_content=self.align_content,
+            justify_content=self.justify_content,
+            gap=self.gap.to_dict(),
             # Spacing
-            self.margin.to_dict(),
-            self.border.to_dict(),
-            self.padding.to_dict(),
+            margin=self.margin.to_dict(),
+            border=self.border.to_dict(),
+            padding=self.padding.to_dict(),
             # Size
-            self.size.to_dict(),
-            self.min_size.to_dict(),
-            self.max_size.to_dict(),
+            size=self._border_box(self.size).to_dict(),
+            min_size=self._border_box(self.min_size).to_dict(),
+            max_size=self._border_box(self.max_size).to_dict(),
+            aspect_ratio=self.aspect_ratio,
             # Flex
-            self.flex_wrap,
-            self.flex_direction,
-            self.flex_grow,
-            self.flex_shrink,
-            self.flex_basis.to_dict(),
+            flex_wrap=self.flex_wrap,
+            flex_direction=self.flex_direction,
+            flex_grow=self.flex_grow,
+            flex_shrink=self.flex_shrink,
+            flex_basis=self.flex_basis.to_dict(),
             # Grid container
-            [e.to_dict() for e in self.grid_template_rows],
-            [e.to_dict() for e in self.grid_template_columns],
-            [e.to_dict() for e in self.grid_auto_rows],
-            [e.to_dict() for e in self.grid_auto_columns],
-            self.grid_auto_flow,
+            grid_template_rows=[e.to_dict() for e in self.grid_template_rows],
+            grid_template_columns=[e.to_dict() for e in self.grid_template_columns],
+            grid_auto_rows=[e.to_dict() for e in self.grid_auto_rows],
+            grid_auto_columns=[e.to_dict() for e in self.grid_auto_columns],
+            grid_auto_flow=self.grid_auto_flow,
+            grid_template_row_names=row_names | self.grid_template_row_names,
+            grid_template_column_names=column_names | self.grid_template_column_names,
             # Grid child
-            self.grid_row.to_dict(),
-            self.grid_column.to_dict(),
-            # Size, optional
-            self.aspect_ratio,
-            # Alignment, optional
-            self.align_items,
-            self.justify_items,
-            self.align_self,
-            self.justify_self,
-            self.align_content,
-            self.justify_content,
+            grid_row=self.grid_row.to_dict(),
+            grid_column=self.grid_column.to_dict(),
         )
 
-    def __attrs_post_init__(self) -> None:
-        object.__setattr__(self, "_Style__ptr", taffylib.style_create(*self.to_args()))
-        logger.debug("style_create() -> %s" % self.__ptr)
-
-    def __del__(self) -> None:
-        if self.__ptr is None:
-            return
-        taffylib.style_drop(self.__ptr)
-        logger.debug("style_drop(ptr: %s)", self.__ptr)
-
-    @property
-    def _ptr(self) -> int:
-        return self.__ptr
-
     def _str(self, args: Optional[tuple[str]] = None) -> str:
         entries = []
         for arg in dir(self):
@@ -270,8 +364,25 @@ class Style:
     def __str__(self) -> str:
         return self._str()
 
+    @classmethod
+    def from_css(cls, css: str) -> Style:
+        """Returns the style for a CSS declaration block, eg.
+        ``"display: flex; flex-direction: column; gap: 10px; padding: 8px 16px"``,
+        optionally enclosed in braces. Supports the same properties as
+        :py:meth:`from_inline`, but raises `ValueError` for properties or values
+        which are not recognized/supported, instead of ignoring them."""
+        css = css.strip()
+        if "{" in css:
+            css = css[css.index("{") + 1 : css.rindex("}")]
+        return cls.from_inline(css, strict=True)
+
     @staticmethod
-    def from_inline(style: str) -> Style:
+    def from_inline(style: str, *, strict: bool = False) -> Style:
+        def unsupported(message: str) -> None:
+            if strict:
+                raise ValueError(message)
+            logger.warning(message)
+
         def parse_style(style: str) -> dict[str, length.Length | str]:
             props = dict()
             for entry in style.split(";"):
@@ -279,9 +390,10 @@ class Style:
                 if not entry:
                     continue
                 name, _, value = entry.partition(":")
+                name = name.strip().lower()
                 if not name.startswith("grid-"):
                     value = parse_value(value)
-                props[name.strip()] = value
+                props[name] = value
             return props
 
         def get_prop_name(prefix: str, key: str, suffix: str = None) -> str:
@@ -305,16 +417,23 @@ class Style:
             top: Iterable[str] = ("top",),
             bottom: Iterable[str] = ("bottom",),
         ) -> rect.Rect:
+            # Shorthand with 1-4 values, eg. `margin: 10px 20px` (`inset` for the
+            # rect without prefix)
             if prefix:
-                for s in (None, suffix) if suffix else (None,):
-                    prop = get_prop_name(prefix, None, s)
-                    if prop in keys:
-                        keys.remove(prop)
-                        values = props[prop]
-                        try:
-                            return rect.Rect(*values)
-                        except TypeError:
-                            return rect.Rect(values)
+                shorthands = [
+                    get_prop_name(prefix, None, s)
+                    for s in ((None, suffix) if suffix else (None,))
+                ]
+            else:
+                shorthands = ["inset"]
+            for prop in shorthands:
+                if prop in keys:
+                    keys.remove(prop)
+                    values = props[prop]
+                    try:
+                        return rect.Rect(*values)
+                    except TypeError:
+                        return rect.Rect(values)
 
             values = [default] * 4
             not_present = True
@@ -389,6 +508,8 @@ class Style:
                     return Overflow
                 case "position":
                     return Position
+                case "box-sizing":
+                    return BoxSizing
                 case "flex-wrap":
                     return FlexWrap
                 case "grid-auto-flow":
@@ -399,7 +520,11 @@ class Style:
             if prop in keys:
                 keys.remove(prop)
                 enum = prop_to_enum(prop)
-                return enum[props[prop].strip().upper().replace("-", "_")]
+                value = props[prop]
+                try:
+                    return enum["_".join(value.split()).upper().replace("-", "_")]
+                except (AttributeError, KeyError):
+                    unsupported(f"Style property {prop}: {value} is not supported")
 
         def to_float(prop: str) -> float:
             if prop in keys:
@@ -431,6 +556,16 @@ class Style:
                 return re.split(" (?![^(,]*\\))", value)
 
             parsed = dict()
+            prop = "grid-template-areas"
+            if prop in keys:
+                value = props[prop]
+                rows = re.findall(r"\"([^\"]*)\"|'([^']*)'", value)
+                if rows:
+                    parsed["grid_template_areas"] = [a or b for a, b in rows]
+                    keys.remove(prop)
+                else:
+                    unsupported(f"Style property {prop}: {value} could not be parsed")
+
             for suffix in ("row", "column"):
                 # grid_template_rows/columns
                 prop = f"grid-template-{suffix}s"
@@ -442,7 +577,7 @@ class Style:
                         ]
                         keys.remove(prop)
                     except ValueError:
-                        logger.warning(
+                        unsupported(
                             f"Style property {prop}: {value} could not be parsed"
                         )
 
@@ -456,7 +591,7 @@ class Style:
                         ]
                         keys.remove(prop)
                     except ValueError:
-                        logger.warning(
+                        unsupported(
                             f"Style property {prop}: {value} could not be parsed"
                         )
 
@@ -470,7 +605,7 @@ class Style:
                         )
                         keys.remove(prop)
                     except ValueError:
-                        logger.warning(
+                        unsupported(
                             f"Style property {prop}: {value} could not be parsed"
                         )
 
@@ -546,6 +681,7 @@ class Style:
             "justify-self",
             "justify-content",
             "position",
+            "box-sizing",
             "grid-auto-flow",
         ):
             v = to_enum(prop)
@@ -554,6 +690,13 @@ class Style:
 
         # float and Dim entries:
         #   flex-basis, flex-grow, flex-shrink, aspect-ratio
+        if props.get("flex-basis") == "content":
+            # Depends on the parent, see `with_content_basis`
+            keys.remove("flex-basis")
+            unsupported(
+                "Style property flex-basis: content is not supported, use "
+                "Style.with_content_basis"
+            )
         for prop in ("flex-basis", "flex-grow", "flex-shrink", "aspect-ratio"):
             v = to_float(prop)
             if v is not None:
@@ -571,8 +714,8 @@ class Style:
 
         # If there are any keys left, these are unrecognized/unsupported
         if len(keys) > 0:
-            for key in keys:
-                logger.warning(f"Style property {key} is not recognized/supported")
+            for key in sorted(keys):
+                unsupported(f"Style property {key} is not recognized/supported")
 
         # values = []
         # for value in args.values():
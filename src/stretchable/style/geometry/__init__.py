@@ -6,9 +6,11 @@ from .length import (
     LengthPoints,
     LengthPointsPercent,
     LengthPointsPercentAuto,
+    LengthPointsPercentAutoCalc,
     Points,
     PointsPercent,
     PointsPercentAuto,
+    PointsPercentAutoCalc,
     AvailableSpace,
     MinTrackSize,
     MaxTrackSize,
@@ -20,6 +22,7 @@ __all__ = [
     "Points",
     "PointsPercent",
     "PointsPercentAuto",
+    "PointsPercentAutoCalc",
     "AvailableSpace",
     "MinTrackSize",
     "MaxTrackSize",
@@ -30,4 +33,5 @@ __all__ = [
     "LengthPoints",
     "LengthPointsPercent",
     "LengthPointsPercentAuto",
+    "LengthPointsPercentAutoCalc",
 ]
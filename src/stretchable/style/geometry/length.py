@@ -21,6 +21,8 @@ class Scale(IntEnum):
     FIT_CONTENT_POINTS = 5
     FIT_CONTENT_PERCENT = 6
     FLEX = 7
+    # A percentage plus an offset in points, see `calc`
+    CALC = 8
 
 
 class Points(IntEnum):
@@ -38,6 +40,13 @@ class PointsPercentAuto(IntEnum):
     PERCENT = Scale.PERCENT
 
 
+class PointsPercentAutoCalc(IntEnum):
+    AUTO = Scale.AUTO
+    POINTS = Scale.POINTS
+    PERCENT = Scale.PERCENT
+    CALC = Scale.CALC
+
+
 class AvailableSpace(IntEnum):
     DEFINITE = Scale.POINTS
     MIN_CONTENT = Scale.MIN_CONTENT
@@ -66,7 +75,7 @@ class MaxTrackSize(IntEnum):
 # @define(frozen=True)
 class LengthBase(Generic[T]):
     _type_T: Any
-    __slots__ = ("scale", "value")
+    __slots__ = ("scale", "value", "offset")
 
     @staticmethod
     def _check_scale(T: type, scale: IntEnum) -> None:
@@ -88,12 +97,16 @@ class LengthBase(Generic[T]):
     def __init_subclass__(cls) -> None:
         cls._type_T = get_args(cls.__orig_bases__[0])[0]
 
-    def __init__(self, scale: T = None, value: float = NAN) -> None:
+    def __init__(
+        self, scale: T = None, value: float = NAN, offset: float = 0.0
+    ) -> None:
         # Check if scale value corresponds to an allowed scale as defined by T.
         if scale:
             LengthBase._check_scale(self._type_T, scale)
         self.scale = scale
         self.value = value
+        # Only used with Scale.CALC, where `value` is the percentage
+        self.offset = offset
 
     def __str__(self) -> str:
         match self.scale:
@@ -115,6 +128,9 @@ class LengthBase(Generic[T]):
                 return f"fit-content({value})"
             case Scale.FLEX:
                 return f"{self.value:.2f} fr" if not isnan(self.value) else "nan"
+            case Scale.CALC:
+                sign = "-" if self.offset < 0 else "+"
+                return f"calc({self.value*100:.2f} % {sign} {abs(self.offset):.2f} pt)"
             case _:
                 return "None"
 
@@ -142,9 +158,11 @@ class LengthBase(Generic[T]):
             raise TypeError("Value is not supported/recognized: " + str(value))
         LengthBase._check_scale(cls._type_T, value.scale)
 
-        return cls(value.scale, value.value)
+        return cls(value.scale, value.value, value.offset)
 
     def to_dict(self) -> dict[str, int | float]:
+        if self.scale == Scale.CALC:
+            return dict(dim=self.scale.value, value=self.value, offset=self.offset)
         return dict(dim=self.scale.value, value=self.value)
 
     def to_pts(self, container: Optional[float] = None) -> float:
@@ -157,6 +175,12 @@ class LengthBase(Generic[T]):
                         "Length scale is PERCENT, `container` dimension must be provided"
                     )
                 return self.value * container
+            case Scale.CALC:
+                if container is None:
+                    raise ValueError(
+                        "Length scale is CALC, `container` dimension must be provided"
+                    )
+                return self.value * container + self.offset
             case scale:
                 raise ValueError(
                     "Length with scale %s cannot be represented in PTS" % scale
@@ -165,8 +189,13 @@ class LengthBase(Generic[T]):
     def __eq__(self, __value: object) -> bool:
         if not isinstance(__value, LengthBase):
             return False
-        return self.scale == __value.scale and (
-            self.value == __value.value or (isnan(self.value) and isnan(__value.value))
+        return (
+            self.scale == __value.scale
+            and (
+                self.value == __value.value
+                or (isnan(self.value) and isnan(__value.value))
+            )
+            and self.offset == __value.offset
         )
 
 
@@ -295,6 +324,12 @@ class LengthPointsPercentAuto(LengthBase[PointsPercentAuto]):
         return LengthPointsPercentAuto(PointsPercentAuto.AUTO, NAN)
 
 
+class LengthPointsPercentAutoCalc(LengthBase[PointsPercentAutoCalc]):
+    @staticmethod
+    def default() -> Self:
+        return LengthPointsPercentAutoCalc(PointsPercentAutoCalc.AUTO, NAN)
+
+
 class LengthMinTrackSize(LengthBase[MinTrackSize]):
     ...
 
@@ -310,7 +345,12 @@ class LengthMaxTrackSize(LengthBase[MaxTrackSize]):
 
     @staticmethod
     def fit_content(value: float | Length) -> LengthMaxTrackSize:
-        if not isinstance(value, Length):
+        """Returns `fit-content(value)`, where `value` is the limit of the track
+        size in either :py:obj:`Scale.POINTS <Scale>` or
+        :py:obj:`Scale.PERCENT <Scale>`."""
+        if value is None:
+            raise TypeError("None value is not supported in this context")
+        if not issubclass(type(value), LengthBase):
             value = LengthMaxTrackSize(MaxTrackSize.FIT_CONTENT_POINTS, value)
         elif value.scale == Scale.POINTS:
             value = LengthMaxTrackSize(MaxTrackSize.FIT_CONTENT_POINTS, value.value)
@@ -324,6 +364,19 @@ class LengthMaxTrackSize(LengthBase[MaxTrackSize]):
         return value
 
 
+def calc(percent: Length, points: float | Length = 0.0) -> Length:
+    """Returns the length `percent` + `points`, like the CSS `calc()` function,
+    eg. ``calc(100 * PCT, -20)`` for `calc(100% - 20px)`. Only supported for
+    the size, min_size and max_size of a style."""
+    if not issubclass(type(percent), LengthBase) or percent.scale != Scale.PERCENT:
+        raise TypeError(f"Only PERCENT is supported for `percent`, not {percent}")
+    if issubclass(type(points), LengthBase):
+        if points.scale != Scale.POINTS:
+            raise TypeError(f"Only POINTS is supported for `points`, not {points}")
+        points = points.value
+    return Length(Scale.CALC, percent.value, points)
+
+
 AUTO = Length(Scale.AUTO)
 PCT = Length(Scale.PERCENT, 0.01)
 PT = Length(Scale.POINTS, 1)
@@ -91,6 +91,16 @@ class RectBase(Generic[T]):
     def __str__(self) -> str:
         return self._str()
 
+    def __eq__(self, __value: object) -> bool:
+        if not isinstance(__value, RectBase):
+            return False
+        return (
+            self.top == __value.top
+            and self.right == __value.right
+            and self.bottom == __value.bottom
+            and self.left == __value.left
+        )
+
 
 class Rect(RectBase[Length]):
     pass
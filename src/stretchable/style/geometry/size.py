@@ -7,6 +7,7 @@ from .length import (
     LengthPoints,
     LengthPointsPercent,
     LengthPointsPercentAuto,
+    LengthPointsPercentAutoCalc,
 )
 
 T = TypeVar("T")
@@ -96,6 +97,10 @@ class SizePointsPercentAuto(SizeBase[LengthPointsPercentAuto]):
     pass
 
 
+class SizePointsPercentAutoCalc(SizeBase[LengthPointsPercentAutoCalc]):
+    pass
+
+
 class SizeAvailableSpace(SizeBase[LengthAvailableSpace]):
     @classmethod
     def default(cls) -> Self:
@@ -31,9 +31,9 @@ def parse_value(
         except ValueError:
             return val
 
-    value = value.strip()
-    if " " in value:
-        return tuple(parse_single(v) for v in value.split(" "))
+    values = value.split()
+    if len(values) > 1:
+        return tuple(parse_single(v) for v in values)
     else:
         return parse_single(value)
 
@@ -82,6 +82,18 @@ class Position(IntEnum):
     ABSOLUTE = 1
 
 
+class BoxSizing(IntEnum):
+    """Whether the size of a node (`size`, `min_size` and `max_size`) includes its
+    padding and border (``BORDER_BOX``) or only its content (``CONTENT_BOX``,
+    same as the default in CSS).
+
+    See `box-sizing <https://developer.mozilla.org/en-US/docs/Web/CSS/box-sizing>`_ on MDN for more information.
+    """
+
+    BORDER_BOX = 0
+    CONTENT_BOX = 1
+
+
 # endregion
 
 # region Alignment
@@ -168,6 +180,15 @@ class AlignContent(IntEnum):
     alignment in the block axis.
 
     See `align-content <https://developer.mozilla.org/en-US/docs/Web/CSS/align-content>`_ on MDN for more information.
+
+    Note
+    ----
+    With a single item (or line), ``SPACE_BETWEEN`` places it at the start,
+    while ``SPACE_EVENLY`` and ``SPACE_AROUND`` center it, same as in CSS. The
+    exception is a wrapping flex container with a single line, where
+    ``SPACE_EVENLY`` and ``SPACE_AROUND`` behave like ``STRETCH`` (the line fills
+    the container, and the items are placed in it by ``align_items``), where
+    browsers center the line. Use ``CENTER`` to center a single line.
     """
 
     START = 0
@@ -188,6 +209,11 @@ class JustifyContent(IntEnum):
     alignment in the inline axis.
 
     See `justify-content <https://developer.mozilla.org/en-US/docs/Web/CSS/justify-content>`_ on MDN for more information.
+
+    Note
+    ----
+    With a single item, ``SPACE_BETWEEN`` places it at the start, while
+    ``SPACE_EVENLY`` and ``SPACE_AROUND`` center it, same as in CSS.
     """
 
     START = 0
@@ -309,7 +335,7 @@ class GridTrackSize:
             value = value.split(",")
             if len(value) != 2:
                 raise ValueError(f"'{value}' not recognized as a valid grid track size")
-            return GridTrackSize(parse_value(value[0]), parse_value(value[1]))
+            return GridTrackSize.minmax(*value)
 
         # Appears to be a specific value (px, % or fr)
         value = parse_value(value)
@@ -372,6 +398,22 @@ class GridTrackSize:
     def fit_content(value: length.PointsPercent | int | float) -> GridTrackSize:
         return GridTrackSize(length.AUTO, length.LengthMaxTrackSize.fit_content(value))
 
+    @staticmethod
+    def minmax(
+        min_size: length.LengthMinTrackSize | int | float | str,
+        max_size: length.LengthMaxTrackSize | int | float | str,
+    ) -> GridTrackSize:
+        """
+        Returns a track size between `min_size` and `max_size`, corresponding to
+        `minmax(min, max)` in CSS. Each size may also be given as a CSS value,
+        eg. `GridTrackSize.minmax(100, "1fr")`.
+        """
+        if isinstance(min_size, str):
+            min_size = parse_value(min_size)
+        if isinstance(max_size, str):
+            max_size = parse_value(max_size)
+        return GridTrackSize(min_size, max_size)
+
     @staticmethod
     def zero() -> GridTrackSize:
         return GridTrackSize(length.ZERO, length.ZERO)
@@ -531,6 +573,7 @@ class GridTrackSizing:
 class GridIndex:
     value: int = None
     span: bool = False
+    name: str = None
 
     # TODO: add validator: index can be != 0, span > 0
 
@@ -546,6 +589,16 @@ class GridIndex:
     def from_span(span: int) -> GridIndex:
         return GridIndex(span, True)
 
+    @staticmethod
+    def from_name(name: str) -> GridIndex:
+        """Returns the index of the line with the given name in the parent, see
+        :py:attr:`Style.grid_template_row_names`. As in CSS, the lines
+        ``<name>-start`` and ``<name>-end`` (such as the lines of a grid area) are
+        used before a line named ``<name>``."""
+        if not re.fullmatch(r"-?[A-Za-z_][\w-]*", name) or name == "auto":
+            raise ValueError(f"'{name}' is not a valid grid line name")
+        return GridIndex(0, name=name)
+
     @staticmethod
     def from_inline(value: str) -> GridIndex:
         value = value.strip()
@@ -557,10 +610,12 @@ class GridIndex:
                 raise ValueError(
                     f"'{value}' is not a recognized as a valid grid-* value"
                 )
+        if value == "auto":
+            return GridIndex.auto()
         try:
             return GridIndex.from_index(int(value))
-        except TypeError:
-            raise ValueError(f"'{value}' is not a recognized as a valid grid-* value")
+        except ValueError:
+            return GridIndex.from_name(value)
 
     @staticmethod
     def from_any(value: object) -> GridIndex:
@@ -576,14 +631,18 @@ class GridIndex:
 
     @property
     def type(self) -> int:
-        if self.value is None:
+        if self.name is not None:
+            return GridIndexType.INDEX
+        elif self.value is None:
             return GridIndexType.AUTO
         elif self.span:
             return GridIndexType.SPAN
         else:
             return GridIndexType.INDEX
 
-    def to_dict(self) -> dict[str, int]:
+    def to_dict(self) -> dict[str, int | str]:
+        if self.name is not None:
+            return dict(kind=self.type.value, value=0, name=self.name)
         return dict(
             kind=self.type.value,
             value=self.value if self.value is not None else 0,
@@ -607,9 +666,11 @@ class GridPlacement:
     def from_inline(value: str) -> GridPlacement:
         if "/" in value:
             start, _, end = value.partition("/")
-        else:
-            start, end = value, None
-        return GridPlacement(start, end)
+            return GridPlacement(start, end)
+        # As in CSS, a single line name (eg. the name of a grid area) is used for
+        # both the start and the end
+        start = GridIndex.from_inline(value)
+        return GridPlacement(start, start if start.name is not None else None)
 
     @staticmethod
     def from_any(value: object) -> GridPlacement:
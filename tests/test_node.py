@@ -1,7 +1,27 @@
+from xml.etree import ElementTree
+
 import pytest
 
-from stretchable import Node
-from stretchable.exceptions import NodeNotFound
+from stretchable import Edge, Node, make_aspect_ratio_measure, taffylib
+from stretchable.context import taffy
+from stretchable.exceptions import LayoutNotComputedError, NodeNotFound
+from stretchable.style import (
+    AUTO,
+    MAX_CONTENT,
+    MIN_CONTENT,
+    PCT,
+    AlignItems,
+    BoxSizing,
+    Display,
+    FlexDirection,
+    FlexWrap,
+    Position,
+    Rect,
+    Style,
+    calc,
+)
+from stretchable.style.geometry.length import AvailableSpace
+from stretchable.style.geometry.size import SizePoints
 
 
 def test_dirty():
@@ -25,6 +45,13 @@ def test_node_replace_child():
         assert root[i].key == f"{i}C"
 
 
+def test_node_key_in_tree():
+    root = Node(key="tree-root").add(Node(key="tree-child"), Node())
+    lines = taffylib.node_print_tree(taffy._ptr, root._ptr).splitlines()
+    assert lines[1].endswith('"tree-root"') and lines[2].endswith('"tree-child"')
+    assert taffylib.find_node_by_key(taffy._ptr, "tree-child") == root[0]._ptr
+
+
 def test_node_find():
     root = Node(key="root").add(
         Node(key="first-child"),
@@ -46,3 +73,247 @@ def test_node_find():
         root.find("/2")
     with pytest.raises(NodeNotFound):
         root.find("2")
+
+
+def test_node_to_svg():
+    root = Node(key="root", size=(200, 100), padding=10).add(
+        Node(key="a", flex_grow=1),
+        Node(key="b", flex_grow=1).add(Node(size=(20, 20))),
+        Node(key="hidden", display=Display.NONE),
+    )
+    with pytest.raises(LayoutNotComputedError):
+        root.to_svg()
+    root.compute_layout()
+
+    svg = ElementTree.fromstring(root.to_svg())
+    ns = "{http://www.w3.org/2000/svg}"
+    assert svg.tag == f"{ns}svg"
+    assert (svg.get("width"), svg.get("height")) == ("200", "100")
+    rects = svg.findall(f"{ns}rect")
+    assert len(rects) == 4
+    assert [rects[0].get(a) for a in ("x", "y", "width", "height")] == [
+        "0",
+        "0",
+        "200",
+        "100",
+    ]
+    assert rects[3].get("x") == rects[2].get("x") != "0"
+    assert all(rect.get("fill") == "none" for rect in rects)
+    assert not svg.findall(f"{ns}text")
+
+    svg = ElementTree.fromstring(
+        root.to_svg(depth_colors=["red", "blue"], stroke="#333", keys=True)
+    )
+    rects = svg.findall(f"{ns}rect")
+    assert [rect.get("fill") for rect in rects] == ["red", "blue", "blue", "red"]
+    assert rects[0].get("stroke") == "#333"
+    assert [text.text for text in svg.findall(f"{ns}text")] == ["root", "a", "b"]
+
+
+def test_node_to_html():
+    root = Node(key="root", size=(200, 100), padding=10).add(
+        Node(key="a", flex_grow=1),
+        Node(flex_grow=1).add(Node(key="c", size=(20, 20))),
+        Node(key="hidden", display=Display.NONE),
+    )
+    with pytest.raises(LayoutNotComputedError):
+        root.to_html()
+    root.compute_layout()
+
+    def parse(html: str) -> list[tuple[str, dict[str, str]]]:
+        divs = ElementTree.fromstring(html).iter("div")
+        return [
+            (div.get("id"), dict(p.split(": ") for p in div.get("style").split("; ")))
+            for div in divs
+        ]
+
+    divs = parse(root.to_html())
+    assert [id for id, _ in divs] == ["root", "a", None, "c"]
+    assert divs[0][1]["position"] == "relative"
+    assert all(style["position"] == "absolute" for _, style in divs[1:])
+    # The boxes are absolute, relative to the root
+    box = root.find("1/0").get_box(relative=False)
+    assert [divs[3][1][prop] for prop in ("left", "top", "width", "height")] == [
+        f"{box.x:g}px",
+        f"{box.y:g}px",
+        "20px",
+        "20px",
+    ]
+    # Tinted by depth
+    colors = [style["background"] for _, style in divs]
+    assert colors[1] == colors[2]
+    assert len({colors[0], colors[1], colors[3]}) == 3
+
+    divs = parse(root.to_html(depth_colors=["red", "blue"]))
+    assert [style["background"] for _, style in divs] == ["red", "blue", "blue", "red"]
+
+
+def test_node_to_ascii():
+    root = Node(size=(100, 40)).add(Node(flex_grow=1), Node(flex_grow=1))
+    root.compute_layout()
+    assert root.to_ascii(20, 6, chars=".ab").split("\n") == [
+        "+--------++--------+",
+        "|aaaaaaaa||aaaaaaaa|",
+        "|aaaaaaaa||aaaaaaaa|",
+        "|aaaaaaaa||aaaaaaaa|",
+        "|aaaaaaaa||aaaaaaaa|",
+        "+--------++--------+",
+    ]
+    # The height follows from the aspect ratio if not provided
+    assert len(root.to_ascii(40).split("\n")) == 8
+
+
+@pytest.mark.parametrize("flex_direction", [FlexDirection.ROW, FlexDirection.COLUMN])
+def test_aspect_ratio_measure(flex_direction):
+    # A 16:9 image fills the width of the container, regardless of its size
+    for width, height in [(1920, 1080), (160, 90)]:
+        image = Node(measure=make_aspect_ratio_measure(width, height))
+        root = Node(size=(320, AUTO), flex_direction=flex_direction).add(image)
+        root.compute_layout()
+        assert (image.border_box.width, image.border_box.height) == (320, 180)
+
+
+def test_aspect_ratio_measure_known_size():
+    # A known width or height is kept, and the other dimension is computed
+    for size, expected in [((160, AUTO), (160, 90)), ((AUTO, 45), (80, 45))]:
+        image = Node(measure=make_aspect_ratio_measure(16, 9), size=size)
+        root = Node(size=(320, AUTO)).add(image)
+        root.compute_layout()
+        assert (image.border_box.width, image.border_box.height) == expected
+
+    with pytest.raises(ValueError):
+        make_aspect_ratio_measure(16, 0)
+
+
+def test_compute_layout_intrinsic_size():
+    # Words of 10 x 10 points each, wrapped to fit the available width
+    def measure_text(known_dimensions, available_space):
+        words = 6
+        match available_space.width.scale:
+            case AvailableSpace.MIN_CONTENT:
+                per_line = 1
+            case AvailableSpace.MAX_CONTENT:
+                per_line = words
+            case _:
+                per_line = max(1, int(available_space.width.value // 10))
+        lines = -(-words // per_line)
+        return SizePoints(min(words, per_line) * 10, lines * 10)
+
+    def text_row():
+        return Node(flex_wrap=FlexWrap.WRAP).add(
+            Node(measure=measure_text), Node(size=(20, 10))
+        )
+
+    # The unwrapped width of the row
+    root = text_row()
+    root.compute_layout((MAX_CONTENT, 100))
+    assert (root.border_box.width, root.border_box.height) == (80, 10)
+    root.compute_layout(MAX_CONTENT)
+    assert (root.border_box.width, root.border_box.height) == (80, 10)
+
+    # The text is wrapped to a word per line, and the fixed size child wraps too
+    root = text_row()
+    root.compute_layout((MIN_CONTENT, MAX_CONTENT))
+    assert (root.border_box.width, root.border_box.height) == (20, 70)
+
+    root = text_row()
+    root.compute_layout((30, MAX_CONTENT))
+    assert (root.border_box.width, root.border_box.height) == (30, 30)
+
+
+@pytest.mark.parametrize(
+    "box_sizing, expected",
+    [(BoxSizing.BORDER_BOX, 100), (BoxSizing.CONTENT_BOX, 130)],
+)
+def test_box_sizing(box_sizing, expected):
+    def layout(**style) -> Node:
+        node = Node(padding=10, border=5, box_sizing=box_sizing, **style)
+        root = Node(size=(500, 500), align_items=AlignItems.START).add(node)
+        root.compute_layout()
+        return node
+
+    node = layout(size=(100, 100))
+    assert (node.border_box.width, node.border_box.height) == (expected, expected)
+    assert node.get_box(Edge.CONTENT).width == expected - 30
+
+    # Percentages (with calc) and min/max sizes as well
+    node = layout(size=(50 * PCT, calc(10 * PCT, 20)), max_size=(AUTO, 60))
+    extra = expected - 100
+    assert (node.border_box.width, node.border_box.height) == (250 + extra, 60 + extra)
+
+
+def test_box_sizing_from_css():
+    style = Style.from_css("width: 100px; padding: 10px; box-sizing: content-box")
+    assert style.box_sizing == BoxSizing.CONTENT_BOX
+    assert style.to_dict()["size"]["width"]["value"] == 120
+    assert Style.from_css("box-sizing: border-box").box_sizing == BoxSizing.BORDER_BOX
+
+
+def test_box_sizing_percentage_padding():
+    with pytest.raises(ValueError, match="percentage padding or border"):
+        Style(padding=10 * PCT, box_sizing=BoxSizing.CONTENT_BOX)
+    Style(padding=10 * PCT, box_sizing=BoxSizing.BORDER_BOX)
+
+
+@pytest.mark.parametrize(
+    "display, static", [(Display.FLEX, (5, 5)), (Display.GRID, (0, 0))]
+)
+def test_absolute_inset(display, static):
+    def layout(width=AUTO, **inset):
+        node = Node(position=Position.ABSOLUTE, inset=Rect(**inset), size=(width, 20))
+        root = Node(display=display, size=(200, 100), padding=5).add(node)
+        root.compute_layout()
+        return node.border_box
+
+    # Both left and right stretch the node (within the padding box of the parent)
+    box = layout(left=10, right=10)
+    assert (box.x, box.width) == (10, 180)
+    box = layout(left=10 * PCT, right=10 * PCT)
+    assert (box.x, box.width) == (20, 160)
+    # With a width as well, left takes precedence over right
+    box = layout(50, left=10, right=10)
+    assert (box.x, box.width) == (10, 50)
+    box = layout(50, right=10, bottom=10)
+    assert (box.x, box.y) == (140, 70)
+    # Auto insets fall back to the static position (the start of the content box
+    # for flexbox, and of the padding box for grid)
+    box = layout(50)
+    assert (box.x, box.y, box.width) == (*static, 50)
+    box = layout(50, left=AUTO, top=30)
+    assert (box.x, box.y) == (static[0], 30)
+
+
+@pytest.mark.parametrize("direction", [FlexDirection.ROW, FlexDirection.COLUMN])
+def test_flex_basis(direction):
+    def main_sizes(*styles: Style) -> list[float]:
+        root = Node(flex_direction=direction, size=(200, 100))
+        root.add(*(Node(style=style) for style in styles))
+        root.compute_layout()
+        boxes = [child.border_box for child in root]
+        if direction == FlexDirection.ROW:
+            return [box.width for box in boxes]
+        return [box.height for box in boxes]
+
+    container = 200 if direction == FlexDirection.ROW else 100
+    # Percentages are of the main axis of the parent
+    assert main_sizes(Style(flex_basis=50 * PCT)) == [container / 2]
+    # An auto flex basis is the size if set (and otherwise the content), and the
+    # remaining space is distributed by flex_grow
+    sized = Style(flex_basis=AUTO, flex_grow=1, size=(20, 20))
+    empty = Style(flex_basis=AUTO, flex_grow=1)
+    free = container - 20
+    assert main_sizes(sized, empty) == [20 + free / 2, free / 2]
+    # Sized by the content instead (there is none), keeping the cross size
+    content = sized.with_content_basis(direction)
+    assert main_sizes(content, empty) == [container / 2] * 2
+    assert content.flex_basis == AUTO
+    assert (content.size.width == AUTO) == (direction == FlexDirection.ROW)
+    assert (content.size.height == AUTO) == (direction == FlexDirection.COLUMN)
+
+
+def test_flex_basis_content_from_css():
+    style = Style.from_inline("flex-basis: content; flex-grow: 1")
+    assert style.flex_basis == AUTO
+    assert style.flex_grow == 1
+    with pytest.raises(ValueError, match="with_content_basis"):
+        Style.from_css("flex-basis: content")
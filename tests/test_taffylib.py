@@ -0,0 +1,2789 @@
+import json
+import logging
+import sys
+import threading
+import time
+import weakref
+
+import pytest
+
+from stretchable import taffylib
+from stretchable.style import (
+    AUTO,
+    FR,
+    MAX_CONTENT,
+    MIN_CONTENT,
+    NAN,
+    PCT,
+    PT,
+    AlignContent,
+    AlignItems,
+    Display,
+    FlexDirection,
+    FlexWrap,
+    GridIndex,
+    GridPlacement,
+    GridTrackSize,
+    JustifyContent,
+    Position,
+    Rect,
+    Size,
+    Style,
+    calc,
+)
+
+
+def test_free_twice():
+    taffy_ptr = taffylib.init()
+    assert taffylib.free(taffy_ptr)
+    assert not taffylib.free(taffy_ptr)
+    # Handles that were never issued are ignored as well
+    for handle in (0, taffy_ptr + 1000, 2**64 - 1):
+        assert not taffylib.free(handle)
+
+
+def test_free_during_compute():
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+    freed = []
+
+    def measure(context, known_width, known_height, available_width, available_height):
+        # The tree is dropped once the layout has been computed
+        freed.append(taffylib.free(taffy_ptr))
+        return (10.0, 10.0)
+
+    taffylib.node_set_measure(taffy_ptr, node, None, measure)
+    taffylib.node_compute_layout(taffy_ptr, node, Size(100 * PT, 100 * PT).to_dict())
+    assert freed[0] and not any(freed[1:])
+    assert not taffylib.free(taffy_ptr)
+    with pytest.raises(KeyError):
+        taffylib.node_get_layout(taffy_ptr, node)
+
+
+def test_use_after_free():
+    taffy_ptr = taffylib.init()
+    node_id = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.free(taffy_ptr)
+    with pytest.raises(KeyError):
+        taffylib.node_create(taffy_ptr, Style().to_dict())
+    with pytest.raises(KeyError):
+        taffylib.node_get_layout(taffy_ptr, node_id)
+    with pytest.raises(KeyError):
+        taffylib.enable_rounding(taffy_ptr)
+
+
+def test_is_rounding_enabled():
+    taffy_ptr = taffylib.init()
+    assert taffylib.is_rounding_enabled(taffy_ptr)
+    taffylib.disable_rounding(taffy_ptr)
+    assert not taffylib.is_rounding_enabled(taffy_ptr)
+    taffylib.disable_rounding(taffy_ptr)
+    assert not taffylib.is_rounding_enabled(taffy_ptr)
+    taffylib.enable_rounding(taffy_ptr)
+    assert taffylib.is_rounding_enabled(taffy_ptr)
+    taffylib.free(taffy_ptr)
+
+
+def test_handles_are_unique():
+    first = taffylib.init()
+    second = taffylib.init()
+    assert first != second
+    taffylib.free(first)
+    taffylib.free(second)
+
+
+def test_invalid_index_raises():
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    style["display"] = 9
+    with pytest.raises(ValueError, match="invalid index 9 for display"):
+        taffylib.node_create(taffy_ptr, style)
+    style = Style().to_dict()
+    style["size"]["width"]["dim"] = 5
+    with pytest.raises(ValueError, match="invalid index 5 for size.width"):
+        taffylib.node_create(taffy_ptr, style)
+    # The tree is still usable after a failed call
+    assert taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize(
+    "field, index",
+    [
+        ("display", 3),
+        ("display", -1),
+        ("flex_direction", 4),
+        ("grid_auto_flow", 4),
+        ("align_items", 7),
+        ("justify_content", 9),
+    ],
+)
+def test_invalid_style_index(field, index):
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    style[field] = index
+    with pytest.raises(ValueError, match=f"invalid index {index} for {field}"):
+        taffylib.node_create(taffy_ptr, style)
+    assert taffylib.total_node_count(taffy_ptr) == 0
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize(
+    "path, dim, field",
+    [
+        (("size", "width"), 99, "size.width"),
+        (("margin", "left"), 99, "margin.left"),
+        (("padding", "top"), 0, "padding.top"),
+        (("flex_basis",), 99, "flex_basis"),
+    ],
+)
+def test_invalid_style_dim(path, dim, field):
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    length = style
+    for key in path:
+        length = length[key]
+    length["dim"] = dim
+    with pytest.raises(ValueError, match=f"invalid index {dim} for {field}"):
+        taffylib.node_create(taffy_ptr, style)
+    assert taffylib.total_node_count(taffy_ptr) == 0
+    taffylib.free(taffy_ptr)
+
+
+def test_invalid_available_space_dim():
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+    available_space = {
+        "width": {"dim": 0, "value": 0},
+        "height": {"dim": 1, "value": 0},
+    }
+    with pytest.raises(ValueError, match="invalid index 0 for available_space.width"):
+        taffylib.node_compute_layout(taffy_ptr, node, available_space)
+    with pytest.raises(ValueError, match="invalid index 0 for available_space.width"):
+        taffylib.compute_layouts(taffy_ptr, [(node, available_space)])
+    taffylib.free(taffy_ptr)
+
+
+def test_invalid_grid_repetition():
+    taffy_ptr = taffylib.init()
+    style = Style(grid_template_columns=[GridTrackSize.from_inline("1fr")]).to_dict()
+    style["grid_template_columns"][0]["repetition"] = -3
+    with pytest.raises(ValueError, match="invalid index -3 for grid_template_columns"):
+        taffylib.node_create(taffy_ptr, style)
+    # Repetitions are stored as 16-bit counts
+    style["grid_template_columns"][0]["repetition"] = 65536
+    with pytest.raises(ValueError, match="invalid index 65536 for grid_template"):
+        taffylib.node_create(taffy_ptr, style)
+    style["grid_template_columns"][0]["repetition"] = 65535
+    node = taffylib.node_create(taffy_ptr, style)
+    style = taffylib.node_get_style(taffy_ptr, node)
+    assert style["grid_template_columns"][0]["repetition"] == 65535
+    # A single track (repetition -2) needs its size
+    style["grid_template_columns"][0] = {"repetition": -2, "single": None, "repeat": []}
+    with pytest.raises(
+        ValueError, match=r"missing track size for grid_template_columns\[0\]"
+    ):
+        taffylib.node_create(taffy_ptr, style)
+    style = Style(grid_template_rows=[GridTrackSize.from_inline("1fr")]).to_dict()
+    style["grid_template_rows"][0]["single"]["max_size"]["dim"] = 99
+    with pytest.raises(
+        ValueError, match=r"invalid index 99 for grid_template_rows\[0\].max_size"
+    ):
+        taffylib.node_create(taffy_ptr, style)
+    taffylib.free(taffy_ptr)
+
+
+def test_invalid_grid_span():
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    style["grid_row"]["end"] = {"kind": 2, "value": -3}
+    with pytest.raises(ValueError, match="invalid index -3 for grid_row.end"):
+        taffylib.node_create(taffy_ptr, style)
+    assert taffylib.total_node_count(taffy_ptr) == 0
+    taffylib.free(taffy_ptr)
+
+
+def test_add_child_invalid_parent():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_drop(taffy_ptr, parent)
+    with pytest.raises(KeyError, match=str(parent)):
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    taffylib.free(taffy_ptr)
+
+
+def test_remove_child_errors():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    other = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, parent, child)
+    with pytest.raises(ValueError, match="Child Node .* is not in the Taffy instance"):
+        taffylib.node_remove_child(taffy_ptr, parent, other)
+    with pytest.raises(ValueError, match="should be < child_count"):
+        taffylib.node_remove_child_at_index(taffy_ptr, parent, 1)
+    with pytest.raises(ValueError, match="should be < child_count"):
+        taffylib.node_replace_child_at_index(taffy_ptr, parent, 1, other)
+    taffylib.node_remove_child(taffy_ptr, parent, child)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_create_ids():
+    taffy_ptr = taffylib.init()
+    ids = [taffylib.node_create(taffy_ptr, Style().to_dict()) for _ in range(10)]
+    assert all(ids)
+    assert len(set(ids)) == len(ids)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_create_fails():
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    style["flex_direction"] = -1
+    with pytest.raises(ValueError):
+        taffylib.node_create(taffy_ptr, style)
+    taffylib.free(taffy_ptr)
+    with pytest.raises(KeyError):
+        taffylib.node_create(taffy_ptr, Style().to_dict())
+
+
+def test_invalid_node_id():
+    taffy_ptr = taffylib.init()
+    taffylib.node_create(taffy_ptr, Style().to_dict())
+    for node_id in (0, 1, 999999):
+        with pytest.raises(KeyError, match=str(node_id)):
+            taffylib.node_get_layout(taffy_ptr, node_id)
+        with pytest.raises(KeyError):
+            taffylib.node_dirty(taffy_ptr, node_id)
+        with pytest.raises(KeyError):
+            taffylib.node_set_style(taffy_ptr, node_id, Style().to_dict())
+    taffylib.free(taffy_ptr)
+
+
+def test_node_children():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    assert taffylib.node_children(taffy_ptr, parent) == []
+    children = [taffylib.node_create(taffy_ptr, Style().to_dict()) for _ in range(3)]
+    for child in children:
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    assert taffylib.node_children(taffy_ptr, parent) == children
+    with pytest.raises(KeyError):
+        taffylib.node_children(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_child_count():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    assert taffylib.node_child_count(taffy_ptr, parent) == 0
+    for _ in range(3):
+        child = taffylib.node_create(taffy_ptr, Style().to_dict())
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    assert taffylib.node_child_count(taffy_ptr, parent) == 3
+    with pytest.raises(KeyError):
+        taffylib.node_child_count(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_parent():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, parent, child)
+    assert taffylib.node_parent(taffy_ptr, child) == parent
+    assert taffylib.node_parent(taffy_ptr, parent) is None
+    taffylib.node_remove_child(taffy_ptr, parent, child)
+    assert taffylib.node_parent(taffy_ptr, child) is None
+    taffylib.free(taffy_ptr)
+
+
+def test_node_is_root():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, parent, child)
+    assert taffylib.node_is_root(taffy_ptr, parent)
+    assert not taffylib.node_is_root(taffy_ptr, child)
+    taffylib.node_remove_child(taffy_ptr, parent, child)
+    assert taffylib.node_is_root(taffy_ptr, child)
+    with pytest.raises(KeyError):
+        taffylib.node_is_root(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_child_at_index():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    children = [taffylib.node_create(taffy_ptr, Style().to_dict()) for _ in range(3)]
+    for child in children:
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    for i, child in enumerate(children):
+        assert taffylib.node_child_at_index(taffy_ptr, parent, i) == child
+    with pytest.raises(IndexError):
+        taffylib.node_child_at_index(taffy_ptr, parent, 3)
+    taffylib.free(taffy_ptr)
+
+
+def test_total_node_count():
+    taffy_ptr = taffylib.init()
+    assert taffylib.total_node_count(taffy_ptr) == 0
+    nodes = [taffylib.node_create(taffy_ptr, Style().to_dict()) for _ in range(5)]
+    assert taffylib.total_node_count(taffy_ptr) == 5
+    taffylib.node_drop(taffy_ptr, nodes[0])
+    assert taffylib.total_node_count(taffy_ptr) == 4
+    taffylib.node_drop_all(taffy_ptr)
+    assert taffylib.total_node_count(taffy_ptr) == 0
+    taffylib.free(taffy_ptr)
+
+
+def test_tree_roots():
+    taffy_ptr = taffylib.init()
+    assert taffylib.tree_roots(taffy_ptr) == []
+    roots = [taffylib.node_create(taffy_ptr, Style().to_dict()) for _ in range(3)]
+    for root in roots:
+        for _ in range(2):
+            child = taffylib.node_create(taffy_ptr, Style().to_dict())
+            taffylib.node_add_child(taffy_ptr, root, child)
+    assert sorted(taffylib.tree_roots(taffy_ptr)) == sorted(roots)
+    taffylib.node_add_child(taffy_ptr, roots[0], roots[1])
+    assert sorted(taffylib.tree_roots(taffy_ptr)) == sorted([roots[0], roots[2]])
+    taffylib.free(taffy_ptr)
+
+
+def _same(a, b):
+    # Compares style dicts, treating NAN values as equal
+    if isinstance(a, dict) and isinstance(b, dict):
+        return a.keys() == b.keys() and all(_same(a[k], b[k]) for k in a)
+    if isinstance(a, list) and isinstance(b, list):
+        return len(a) == len(b) and all(_same(x, y) for x, y in zip(a, b))
+    if isinstance(a, float) and isinstance(b, float) and a != a and b != b:
+        return True
+    return a == b
+
+
+def test_node_get_style():
+    taffy_ptr = taffylib.init()
+    style = Style(
+        display=Display.GRID,
+        position=Position.ABSOLUTE,
+        align_items=AlignItems.CENTER,
+        justify_content=JustifyContent.SPACE_EVENLY,
+        flex_direction=FlexDirection.COLUMN_REVERSE,
+        flex_grow=2,
+        margin=10 * PT,
+        size=Size(50 * PCT, AUTO),
+        aspect_ratio=1.5,
+    ).to_dict()
+    track = dict(
+        min_size=dict(dim=1, value=10.0), max_size=dict(dim=7, value=1.0)
+    )
+    style["grid_template_rows"] = [
+        dict(repetition=-2, single=track, repeat=[]),
+        dict(repetition=3, single=None, repeat=[track, track]),
+        dict(repetition=-1, single=None, repeat=[track]),
+    ]
+    style["grid_auto_columns"] = [
+        dict(min_size=dict(dim=3, value=NAN), max_size=dict(dim=6, value=0.5)),
+    ]
+    style["grid_row"] = dict(
+        start=dict(kind=1, value=-2), end=dict(kind=2, value=3)
+    )
+    node = taffylib.node_create(taffy_ptr, style)
+    assert _same(taffylib.node_get_style(taffy_ptr, node), style)
+    taffylib.node_set_style(taffy_ptr, node, Style().to_dict())
+    assert _same(taffylib.node_get_style(taffy_ptr, node), Style().to_dict())
+    with pytest.raises(KeyError):
+        taffylib.node_get_style(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_set_children():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    children = [taffylib.node_create(taffy_ptr, Style().to_dict()) for _ in range(5)]
+    for child in children:
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    reordered = [children[i] for i in (3, 0, 4, 2, 1)]
+    taffylib.node_set_children(taffy_ptr, parent, reordered)
+    assert taffylib.node_children(taffy_ptr, parent) == reordered
+    for child in reordered:
+        assert taffylib.node_parent(taffy_ptr, child) == parent
+    assert taffylib.node_dirty(taffy_ptr, parent)
+    # An invalid child id leaves the existing children untouched
+    with pytest.raises(KeyError, match="999999"):
+        taffylib.node_set_children(taffy_ptr, parent, [children[0], 999999])
+    assert taffylib.node_children(taffy_ptr, parent) == reordered
+    taffylib.free(taffy_ptr)
+
+
+def test_node_exists():
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+    assert taffylib.node_exists(taffy_ptr, node)
+    taffylib.node_drop(taffy_ptr, node)
+    assert not taffylib.node_exists(taffy_ptr, node)
+    for node_id in (0, 1, 2**64 - 1):
+        assert not taffylib.node_exists(taffy_ptr, node_id)
+    # Only ids created in this tree are valid, also once a slot is reused
+    reused = taffylib.node_create(taffy_ptr, Style().to_dict())
+    assert reused != node
+    assert not taffylib.node_exists(taffy_ptr, node)
+    other_ptr = taffylib.init()
+    other = taffylib.nodes_create(other_ptr, [Style().to_dict()] * 3)[-1]
+    assert not taffylib.node_exists(taffy_ptr, other)
+    with pytest.raises(KeyError):
+        taffylib.node_get_layout(taffy_ptr, other)
+    taffylib.free(other_ptr)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_clear_children():
+    taffy_ptr = taffylib.init()
+    parent, node = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    grandchild = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, parent, node)
+    taffylib.node_set_children(taffy_ptr, node, children)
+    taffylib.node_add_child(taffy_ptr, children[0], grandchild)
+    taffylib.node_compute_layout(taffy_ptr, parent, Size(100 * PT, 100 * PT).to_dict())
+
+    taffylib.node_clear_children(taffy_ptr, node)
+    assert taffylib.node_child_count(taffy_ptr, node) == 0
+    assert taffylib.node_exists(taffy_ptr, node)
+    assert taffylib.node_exists(taffy_ptr, parent)
+    assert taffylib.node_parent(taffy_ptr, node) == parent
+    assert taffylib.node_dirty(taffy_ptr, node)
+    assert not any(taffylib.node_exists(taffy_ptr, child) for child in children)
+    # Only the direct children are dropped
+    assert taffylib.node_exists(taffy_ptr, grandchild)
+    assert taffylib.node_parent(taffy_ptr, grandchild) is None
+    taffylib.free(taffy_ptr)
+
+
+def test_node_get_unrounded_layout():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(size=Size(100 * PT, 10 * PT)).to_dict())
+    children = [
+        taffylib.node_create(taffy_ptr, Style(flex_grow=1).to_dict()) for _ in range(3)
+    ]
+    taffylib.node_set_children(taffy_ptr, root, children)
+    available_space = Size(100 * PT, 10 * PT).to_dict()
+
+    taffylib.enable_rounding(taffy_ptr)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    rounded = [taffylib.node_get_layout(taffy_ptr, c) for c in children]
+    unrounded = [taffylib.node_get_unrounded_layout(taffy_ptr, c) for c in children]
+    assert [layout["width"] for layout in rounded] == [33, 34, 33]
+    assert [layout["left"] for layout in rounded] == [0, 33, 67]
+    for layout in unrounded:
+        assert layout["width"] == pytest.approx(100 / 3)
+    assert rounded != unrounded
+
+    taffylib.disable_rounding(taffy_ptr)
+    taffylib.node_mark_dirty(taffy_ptr, root)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    for child in children:
+        assert taffylib.node_get_layout(
+            taffy_ptr, child
+        ) == taffylib.node_get_unrounded_layout(taffy_ptr, child)
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize("rounding", [True, False])
+def test_node_get_size_and_location(rounding):
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(200 * PT, 100 * PT), padding=10 * PT).to_dict()
+    )
+    children = taffylib.nodes_create(
+        taffy_ptr, [Style(flex_grow=i, margin=1.25 * PT).to_dict() for i in (1, 2)]
+    )
+    taffylib.node_set_children(taffy_ptr, root, children)
+    if not rounding:
+        taffylib.disable_rounding(taffy_ptr)
+    taffylib.set_direction(taffy_ptr, True)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 100 * PT).to_dict())
+    for node in [root, *children]:
+        layout = taffylib.node_get_layout(taffy_ptr, node)
+        size = taffylib.node_get_size(taffy_ptr, node)
+        location = taffylib.node_get_location(taffy_ptr, node)
+        assert size == (layout["width"], layout["height"])
+        assert location == (layout["left"], layout["top"])
+    with pytest.raises(KeyError):
+        taffylib.node_get_size(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_get_absolute_layout():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(200 * PT, 200 * PT), padding=10 * PT).to_dict()
+    )
+    child = taffylib.node_create(
+        taffy_ptr, Style(margin=5 * PT, padding=7.5 * PT, flex_grow=1).to_dict()
+    )
+    grandchild = taffylib.node_create(
+        taffy_ptr, Style(margin=3.25 * PT, size=Size(20 * PT, 20 * PT)).to_dict()
+    )
+    taffylib.node_add_child(taffy_ptr, root, child)
+    taffylib.node_add_child(taffy_ptr, child, grandchild)
+    available_space = Size(200 * PT, 200 * PT).to_dict()
+
+    taffylib.disable_rounding(taffy_ptr)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    left, top = 0, 0
+    for node in (root, child, grandchild):
+        layout = taffylib.node_get_layout(taffy_ptr, node)
+        left += layout["left"]
+        top += layout["top"]
+        absolute = taffylib.node_get_absolute_layout(taffy_ptr, node)
+        assert absolute["left"] == pytest.approx(left)
+        assert absolute["top"] == pytest.approx(top)
+        assert absolute["width"] == layout["width"]
+        assert absolute["height"] == layout["height"]
+    assert (left, top) == pytest.approx((25.75, 25.75))
+
+    taffylib.enable_rounding(taffy_ptr)
+    taffylib.node_mark_dirty(taffy_ptr, root)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    absolute = taffylib.node_get_absolute_layout(taffy_ptr, grandchild)
+    assert (absolute["left"], absolute["top"]) == (26, 26)
+    assert absolute["width"] == taffylib.node_get_layout(taffy_ptr, grandchild)["width"]
+    taffylib.free(taffy_ptr)
+
+
+def test_node_scroll_offset():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    # A scrollable list of 10 rows, 20 points each
+    style = Style(flex_direction=FlexDirection.COLUMN, size=Size(50 * PT, 50 * PT))
+    scroller = taffylib.node_create(taffy_ptr, style.to_dict())
+    rows = taffylib.nodes_create(
+        taffy_ptr, [Style(size=Size(50 * PT, 20 * PT), flex_shrink=0).to_dict()] * 10
+    )
+    taffylib.node_add_child(taffy_ptr, root, scroller)
+    taffylib.node_set_children(taffy_ptr, scroller, rows)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert taffylib.node_get_scroll_offset(taffy_ptr, scroller) == (0, 0)
+
+    def absolute(node):
+        layout = taffylib.node_get_absolute_layout(taffy_ptr, node)
+        return (layout["left"], layout["top"])
+
+    taffylib.node_set_scroll_offset(taffy_ptr, scroller, 5, 30)
+    assert taffylib.node_get_scroll_offset(taffy_ptr, scroller) == (5, 30)
+    # The descendants move, but the node itself and the relative layouts do not
+    assert absolute(scroller) == (0, 0)
+    assert absolute(rows[0]) == (-5, -30)
+    assert absolute(rows[3]) == (-5, 30)
+    assert taffylib.node_get_layout(taffy_ptr, rows[3])["top"] == 60
+    assert taffylib.node_at_point(taffy_ptr, root, 10, 10) == rows[2]
+
+    # Kept when the layout is computed again
+    taffylib.node_mark_dirty(taffy_ptr, root)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert absolute(rows[3]) == (-5, 30)
+
+    taffylib.node_set_scroll_offset(taffy_ptr, scroller, 0, 0)
+    assert absolute(rows[3]) == (0, 60)
+    with pytest.raises(ValueError):
+        taffylib.node_set_scroll_offset(taffy_ptr, scroller, NAN, 0)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_get_box():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(200 * PT, 200 * PT)).to_dict()
+    )
+    node = taffylib.node_create(
+        taffy_ptr,
+        Style(
+            size=Size(100 * PT, 80 * PT),
+            border=2 * PT,
+            padding=10 * PCT,
+            margin=5 * PT,
+        ).to_dict(),
+    )
+    taffylib.node_add_child(taffy_ptr, root, node)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 200 * PT).to_dict())
+
+    # Content, padding, border and margin box (padding is 10% of the parent width)
+    boxes = [taffylib.node_get_box(taffy_ptr, node, edge) for edge in range(4)]
+    assert boxes == [
+        [27, 27, 56, 36],
+        [7, 7, 96, 76],
+        [5, 5, 100, 80],
+        [0, 0, 110, 90],
+    ]
+    content = boxes[0]
+    assert content[2] == 100 - 2 * 2 - 2 * 20
+    assert content[3] == 80 - 2 * 2 - 2 * 20
+
+    taffylib.node_set_style(taffy_ptr, root, Style(padding=10 * PCT).to_dict())
+    taffylib.node_set_style(taffy_ptr, node, Style(margin=AUTO).to_dict())
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 200 * PT).to_dict())
+    with pytest.raises(ValueError, match="without a parent"):
+        taffylib.node_get_box(taffy_ptr, root, 0)
+    with pytest.raises(ValueError, match="auto margins"):
+        taffylib.node_get_box(taffy_ptr, node, 3)
+    assert taffylib.node_get_box(taffy_ptr, node, 0)[2:] == [0, 0]
+    with pytest.raises(ValueError, match="invalid index 4 for edge"):
+        taffylib.node_get_box(taffy_ptr, node, 4)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_get_box_padded_parent():
+    taffy_ptr = taffylib.init()
+    style = Style(size=Size(100 * PT, 100 * PT), padding=20 * PT)
+    root = taffylib.node_create(taffy_ptr, style.to_dict())
+    style = Style(
+        size=Size(50 * PT, 50 * PT), padding=10 * PCT, margin=10 * PCT, flex_shrink=0
+    )
+    node = taffylib.node_create(taffy_ptr, style.to_dict())
+    taffylib.node_add_child(taffy_ptr, root, node)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+
+    # Percentages are of the content width of the parent (60), as in taffy
+    assert taffylib.node_get_box(taffy_ptr, node, 0) == [32, 32, 38, 38]
+    assert taffylib.node_get_box(taffy_ptr, node, 3) == [20, 20, 62, 62]
+    child = taffylib.node_create(taffy_ptr, Style(flex_grow=1).to_dict())
+    taffylib.node_add_child(taffy_ptr, node, child)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+    content = taffylib.node_get_box(taffy_ptr, node, 0)
+    assert content[2] == taffylib.node_get_layout(taffy_ptr, child)["width"]
+    taffylib.free(taffy_ptr)
+
+
+def test_subtree_get_layouts():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(flex_wrap=FlexWrap.WRAP).to_dict())
+    nodes = [root]
+    for i in range(1, 1000):
+        node = taffylib.node_create(
+            taffy_ptr, Style(size=Size(10 * PT, 10 * PT), padding=1 * PT).to_dict()
+        )
+        taffylib.node_add_child(taffy_ptr, nodes[(i - 1) // 10], node)
+        nodes.append(node)
+    taffylib.node_compute_layout(
+        taffy_ptr, root, Size(1000 * PT, 1000 * PT).to_dict()
+    )
+
+    start = time.perf_counter()
+    layouts = taffylib.subtree_get_layouts(taffy_ptr, root)
+    batched = time.perf_counter() - start
+    start = time.perf_counter()
+    expected = [taffylib.node_get_layout(taffy_ptr, node) for node in nodes]
+    individual = time.perf_counter() - start
+    print(f"subtree_get_layouts: {batched:.6f}s, node_get_layout: {individual:.6f}s")
+
+    assert len(layouts) == len(nodes)
+    assert dict(layouts) == dict(zip(nodes, expected))
+    # Parents come before their children
+    assert layouts[0][0] == root
+    seen = set()
+    for node, _ in layouts:
+        parent = taffylib.node_parent(taffy_ptr, node)
+        assert parent is None or parent in seen
+        seen.add(node)
+    taffylib.free(taffy_ptr)
+
+
+def test_layout_diff():
+    taffy_ptr = taffylib.init()
+    style = Style(size=Size(10 * PT, 10 * PT))
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    a, b, c = taffylib.nodes_create(taffy_ptr, [style.to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, root, [a, b, c])
+    grandchild = taffylib.node_create(taffy_ptr, style.to_dict())
+    taffylib.node_add_child(taffy_ptr, a, grandchild)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+
+    previous = taffylib.subtree_get_layouts(taffy_ptr, root)
+    assert taffylib.layout_diff(taffy_ptr, root, previous) == []
+
+    # Resizing b shifts c, but not a (or its child)
+    taffylib.node_set_style(taffy_ptr, b, Style(size=Size(30 * PT, 10 * PT)).to_dict())
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    diff = taffylib.layout_diff(taffy_ptr, root, previous)
+    assert [node for node, _, _ in diff] == [b, c]
+    (_, b_before, b_after), (_, c_before, c_after) = diff
+    assert (b_before["width"], b_after["width"]) == (10, 30)
+    assert (c_before["left"], c_after["left"]) == (20, 40)
+    assert c_before["width"] == c_after["width"]
+
+    # Nodes without a previous layout are not included
+    assert taffylib.layout_diff(taffy_ptr, root, previous[:2]) == []
+    taffylib.free(taffy_ptr)
+
+
+def test_node_get_layout_tree():
+    taffy_ptr = taffylib.init()
+    style = Style(size=Size(10 * PT, 10 * PT), padding=1 * PT).to_dict()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    children = taffylib.nodes_create(taffy_ptr, [style] * 2)
+    grandchildren = taffylib.nodes_create(taffy_ptr, [style] * 3)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    taffylib.node_set_children(taffy_ptr, children[1], grandchildren)
+    taffylib.node_compute_layout(
+        taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict()
+    )
+
+    def expected(node):
+        return dict(
+            id=node,
+            layout=taffylib.node_get_layout(taffy_ptr, node),
+            children=[
+                expected(child) for child in taffylib.node_children(taffy_ptr, node)
+            ],
+        )
+
+    tree = taffylib.node_get_layout_tree(taffy_ptr, root)
+    assert tree == expected(root)
+    assert [child["id"] for child in tree["children"]] == children
+    assert [child["id"] for child in tree["children"][1]["children"]] == grandchildren
+    assert tree["children"][1]["children"][2]["children"] == []
+    assert "key" not in tree
+
+    # Keys are included for nodes with a key
+    taffylib.node_set_key(taffy_ptr, children[1], "list")
+    taffylib.node_set_key(taffy_ptr, grandchildren[0], "item")
+    tree = taffylib.node_get_layout_tree(taffy_ptr, root)
+    assert [child.get("key") for child in tree["children"]] == [None, "list"]
+    assert tree["children"][1]["children"][0]["key"] == "item"
+    assert "key" not in tree["children"][1]["children"][1]
+    with pytest.raises(KeyError):
+        taffylib.node_get_layout_tree(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_children_layouts():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    children = taffylib.nodes_create(
+        taffy_ptr,
+        [Style(size=Size((i + 1) * 10 * PT, 10 * PT)).to_dict() for i in range(4)],
+    )
+    taffylib.node_set_children(taffy_ptr, root, children)
+    grandchild = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, children[0], grandchild)
+    taffylib.node_compute_layout(
+        taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict()
+    )
+
+    layouts = taffylib.node_children_layouts(taffy_ptr, root)
+    assert [node for node, _ in layouts] == taffylib.node_children(taffy_ptr, root)
+    assert [layout for _, layout in layouts] == [
+        taffylib.node_get_layout(taffy_ptr, child) for child in children
+    ]
+    assert [layout["width"] for _, layout in layouts] == [10, 20, 30, 40]
+    assert taffylib.node_children_layouts(taffy_ptr, grandchild) == []
+    with pytest.raises(KeyError):
+        taffylib.node_children_layouts(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_nodes_create():
+    taffy_ptr = taffylib.init()
+    ids = taffylib.nodes_create(taffy_ptr, [Style().to_dict() for _ in range(100)])
+    assert len(ids) == 100
+    assert len(set(ids)) == 100
+    assert all(taffylib.node_exists(taffy_ptr, node_id) for node_id in ids)
+    assert taffylib.nodes_create(taffy_ptr, []) == []
+
+    # No nodes are created if any of the styles is invalid
+    invalid = Style().to_dict()
+    invalid["display"] = 9
+    with pytest.raises(ValueError, match="invalid index 9"):
+        taffylib.nodes_create(taffy_ptr, [Style().to_dict(), invalid])
+    assert taffylib.total_node_count(taffy_ptr) == 100
+    taffylib.free(taffy_ptr)
+
+
+def test_default_style():
+    taffy_ptr = taffylib.init()
+    # Without a default style, nodes have the default style of taffy (which has no
+    # grid tracks, unlike the default `Style`)
+    node = taffylib.node_create_default(taffy_ptr)
+    style = Style(
+        grid_template_rows=[],
+        grid_template_columns=[],
+        grid_auto_rows=[],
+        grid_auto_columns=[],
+    )
+    assert _same(taffylib.node_get_style(taffy_ptr, node), style.to_dict())
+
+    default = Style(
+        flex_direction=FlexDirection.COLUMN, padding=4 * PT, size=Size(AUTO, 50 * PCT)
+    )
+    taffylib.set_default_style(taffy_ptr, default.to_dict())
+    for node in (
+        taffylib.node_create_default(taffy_ptr),
+        taffylib.node_create(taffy_ptr),
+    ):
+        assert _same(taffylib.node_get_style(taffy_ptr, node), default.to_dict())
+
+    # An explicit style replaces the default style entirely
+    style = Style(flex_grow=1)
+    node = taffylib.node_create(taffy_ptr, style.to_dict())
+    assert _same(taffylib.node_get_style(taffy_ptr, node), style.to_dict())
+
+    # Existing nodes keep their style when the default style changes
+    taffylib.set_default_style(taffy_ptr, Style().to_dict())
+    assert taffylib.node_get_style(taffy_ptr, node)["flex_grow"] == 1
+    node = taffylib.node_create_default(taffy_ptr)
+    assert _same(taffylib.node_get_style(taffy_ptr, node), Style().to_dict())
+
+    with pytest.raises(ValueError):
+        taffylib.set_default_style(taffy_ptr, Style(aspect_ratio=-1).to_dict())
+    taffylib.free(taffy_ptr)
+
+
+def test_node_create_with_children():
+    taffy_ptr = taffylib.init()
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict() for _ in range(3)])
+    children.reverse()
+    node = taffylib.node_create_with_children(
+        taffy_ptr, Style(display=Display.GRID).to_dict(), children
+    )
+    assert taffylib.node_children(taffy_ptr, node) == children
+    assert all(taffylib.node_parent(taffy_ptr, child) == node for child in children)
+    assert taffylib.node_get_style(taffy_ptr, node)["display"] == Display.GRID
+    leaf = taffylib.node_create_with_children(taffy_ptr, Style().to_dict(), [])
+    assert taffylib.node_children(taffy_ptr, leaf) == []
+
+    # No node is created if any of the children is invalid
+    with pytest.raises(KeyError):
+        taffylib.node_create_with_children(
+            taffy_ptr, Style().to_dict(), [children[0], 999999]
+        )
+    assert taffylib.total_node_count(taffy_ptr) == 5
+    taffylib.free(taffy_ptr)
+
+
+def test_reserve():
+    taffy_ptr = taffylib.init()
+    taffylib.reserve(taffy_ptr, 1000)
+    taffylib.reserve(taffy_ptr, 0)
+    ids = taffylib.nodes_create(taffy_ptr, [Style().to_dict() for _ in range(1000)])
+    assert taffylib.total_node_count(taffy_ptr) == 1000
+    assert all(taffylib.node_exists(taffy_ptr, node_id) for node_id in ids)
+    taffylib.free(taffy_ptr)
+    with pytest.raises(KeyError):
+        taffylib.reserve(taffy_ptr, 10)
+
+
+def test_nodes_set_styles():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    ids = taffylib.nodes_create(taffy_ptr, [Style().to_dict() for _ in range(100)])
+    taffylib.node_set_children(taffy_ptr, root, ids)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 200 * PT).to_dict())
+    taffylib.nodes_set_styles(
+        taffy_ptr,
+        [(node_id, Style(flex_grow=i).to_dict()) for i, node_id in enumerate(ids)],
+    )
+    for i, node_id in enumerate(ids):
+        assert taffylib.node_get_style(taffy_ptr, node_id)["flex_grow"] == i
+        assert taffylib.node_dirty(taffy_ptr, node_id)
+    taffylib.nodes_set_styles(taffy_ptr, [])
+
+    # No styles are changed if any of the nodes or styles is invalid
+    with pytest.raises(KeyError):
+        taffylib.nodes_set_styles(
+            taffy_ptr, [(ids[0], Style().to_dict()), (999999, Style().to_dict())]
+        )
+    invalid = Style().to_dict()
+    invalid["display"] = 9
+    with pytest.raises(ValueError, match="invalid index 9"):
+        taffylib.nodes_set_styles(
+            taffy_ptr, [(ids[0], Style().to_dict()), (ids[1], invalid)]
+        )
+    assert taffylib.node_get_style(taffy_ptr, ids[0])["flex_grow"] == 0
+    assert taffylib.node_get_style(taffy_ptr, ids[1])["flex_grow"] == 1
+    taffylib.free(taffy_ptr)
+
+
+def test_node_print_tree():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(size=Size(120 * PT, 80 * PT)).to_dict())
+    children = taffylib.nodes_create(
+        taffy_ptr, [Style(flex_grow=1).to_dict() for _ in range(2)]
+    )
+    taffylib.node_set_children(taffy_ptr, root, children)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 200 * PT).to_dict())
+    output = taffylib.node_print_tree(taffy_ptr, root)
+    lines = output.splitlines()
+    assert lines[0] == "TREE"
+    assert len(lines) == 4
+    assert "FLEX" in lines[1] and "width: 120" in lines[1] and "height: 80" in lines[1]
+    assert lines[1].endswith(f"({root})")
+    assert "├── " in lines[2] and lines[2].endswith(f"({children[0]})")
+    assert "└── " in lines[3] and "x: 60" in lines[3]
+    taffylib.free(taffy_ptr)
+
+
+def test_node_key():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    assert taffylib.node_get_key(taffy_ptr, root) is None
+    assert taffylib.find_node_by_key(taffy_ptr, "header") is None
+
+    taffylib.node_set_key(taffy_ptr, children[1], "header")
+    assert taffylib.node_get_key(taffy_ptr, children[1]) == "header"
+    assert taffylib.find_node_by_key(taffy_ptr, "header") == children[1]
+    # Keys need not be unique, the node with the lowest id is found
+    taffylib.node_set_key(taffy_ptr, children[2], "header")
+    assert taffylib.find_node_by_key(taffy_ptr, "header") == min(children[1:])
+
+    # Shown in the printed tree and included in (and restored from) the JSON
+    lines = taffylib.node_print_tree(taffy_ptr, root).splitlines()
+    assert lines[3].endswith(f'({children[1]}) "header"')
+    assert lines[2].endswith(f"({children[0]})")
+    tree = json.loads(taffylib.tree_to_json(taffy_ptr, root))
+    assert "key" not in tree
+    keys = [child.get("key") for child in tree["children"]]
+    assert keys == [None, "header", "header"]
+    copy = taffylib.tree_from_json(taffy_ptr, json.dumps(tree))
+    copy_children = taffylib.node_children(taffy_ptr, copy)
+    assert taffylib.node_get_key(taffy_ptr, copy_children[1]) == "header"
+
+    taffylib.node_set_key(taffy_ptr, children[1], None)
+    taffylib.node_drop(taffy_ptr, children[2])
+    assert taffylib.find_node_by_key(taffy_ptr, "header") == min(copy_children[1:])
+    taffylib.free(taffy_ptr)
+
+
+def test_node_clone_subtree():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(padding=5 * PT).to_dict())
+    child = taffylib.node_create(taffy_ptr, Style(flex_grow=1).to_dict())
+    grandchildren = taffylib.nodes_create(
+        taffy_ptr, [Style(size=Size(i * PT, i * PT)).to_dict() for i in (10, 20)]
+    )
+    taffylib.node_add_child(taffy_ptr, root, child)
+    taffylib.node_set_children(taffy_ptr, child, grandchildren)
+
+    clone = taffylib.node_clone_subtree(taffy_ptr, root)
+    assert clone != root
+    assert taffylib.total_node_count(taffy_ptr) == 8
+    assert taffylib.node_parent(taffy_ptr, clone) is None
+    assert _same(
+        taffylib.node_get_style(taffy_ptr, clone),
+        taffylib.node_get_style(taffy_ptr, root),
+    )
+    (clone_child,) = taffylib.node_children(taffy_ptr, clone)
+    clone_grandchildren = taffylib.node_children(taffy_ptr, clone_child)
+    assert clone_child != child
+    assert not set(clone_grandchildren) & set(grandchildren)
+    for original, copy in zip(grandchildren, clone_grandchildren):
+        assert _same(
+            taffylib.node_get_style(taffy_ptr, copy),
+            taffylib.node_get_style(taffy_ptr, original),
+        )
+
+    # The clone is independent of the original
+    taffylib.node_set_style(taffy_ptr, clone_child, Style(flex_grow=3).to_dict())
+    taffylib.node_remove_child_at_index(taffy_ptr, clone_child, 0)
+    assert taffylib.node_get_style(taffy_ptr, child)["flex_grow"] == 1
+    assert taffylib.node_children(taffy_ptr, child) == grandchildren
+    taffylib.free(taffy_ptr)
+
+
+def test_node_reparent():
+    taffy_ptr = taffylib.init()
+    first, second = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    child, grandchild = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    siblings = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    taffylib.node_add_child(taffy_ptr, first, child)
+    taffylib.node_add_child(taffy_ptr, child, grandchild)
+    taffylib.node_set_children(taffy_ptr, second, siblings)
+
+    taffylib.node_reparent(taffy_ptr, child, second, 1)
+    assert taffylib.node_children(taffy_ptr, first) == []
+    assert taffylib.node_children(taffy_ptr, second) == [siblings[0], child, siblings[1]]
+    assert taffylib.node_parent(taffy_ptr, child) == second
+    assert taffylib.node_children(taffy_ptr, child) == [grandchild]
+
+    # Moving within the same parent
+    taffylib.node_reparent(taffy_ptr, child, second, 2)
+    assert taffylib.node_children(taffy_ptr, second) == siblings + [child]
+
+    # Nodes without a parent can be moved as well
+    taffylib.node_reparent(taffy_ptr, first, second, 0)
+    assert taffylib.node_children(taffy_ptr, second)[0] == first
+
+    with pytest.raises(IndexError):
+        taffylib.node_reparent(taffy_ptr, child, first, 1)
+    with pytest.raises(ValueError, match="own subtree"):
+        taffylib.node_reparent(taffy_ptr, child, grandchild, 0)
+    with pytest.raises(KeyError):
+        taffylib.node_reparent(taffy_ptr, child, 999999, 0)
+    assert taffylib.node_parent(taffy_ptr, child) == second
+    taffylib.free(taffy_ptr)
+
+
+def test_node_detach():
+    taffy_ptr = taffylib.init()
+    first, second = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    child, grandchild = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    taffylib.node_add_child(taffy_ptr, first, child)
+    taffylib.node_add_child(taffy_ptr, child, grandchild)
+
+    taffylib.node_detach(taffy_ptr, child)
+    assert taffylib.node_exists(taffy_ptr, child)
+    assert taffylib.node_parent(taffy_ptr, child) is None
+    assert taffylib.node_children(taffy_ptr, first) == []
+    assert taffylib.node_children(taffy_ptr, child) == [grandchild]
+    with pytest.raises(ValueError, match="no parent"):
+        taffylib.node_detach(taffy_ptr, child)
+
+    # The subtree is kept when attaching it again
+    taffylib.node_add_child(taffy_ptr, second, child)
+    assert taffylib.node_parent(taffy_ptr, child) == second
+    assert taffylib.node_children(taffy_ptr, child) == [grandchild]
+    assert taffylib.node_parent(taffy_ptr, grandchild) == child
+    taffylib.free(taffy_ptr)
+
+
+def test_node_replace_subtree():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, parent, children)
+    grandchild = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, children[1], grandchild)
+    new_root, new_child = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    taffylib.node_add_child(taffy_ptr, new_root, new_child)
+
+    # The siblings keep their order, and the old subtree is kept as a root
+    taffylib.node_replace_subtree(taffy_ptr, children[1], new_root)
+    assert taffylib.node_children(taffy_ptr, parent) == [
+        children[0],
+        new_root,
+        children[2],
+    ]
+    assert taffylib.node_parent(taffy_ptr, new_root) == parent
+    assert taffylib.node_children(taffy_ptr, new_root) == [new_child]
+    assert taffylib.node_parent(taffy_ptr, children[1]) is None
+    assert taffylib.node_children(taffy_ptr, children[1]) == [grandchild]
+
+    # Replacing with a node from elsewhere in the tree moves it, and the old
+    # subtree can be dropped
+    taffylib.node_replace_subtree(taffy_ptr, new_root, children[0], drop=True)
+    assert taffylib.node_children(taffy_ptr, parent) == [children[0], children[2]]
+    assert not taffylib.node_exists(taffy_ptr, new_root)
+    assert not taffylib.node_exists(taffy_ptr, new_child)
+
+    with pytest.raises(ValueError, match="no parent"):
+        taffylib.node_replace_subtree(taffy_ptr, parent, children[1])
+    with pytest.raises(ValueError, match="own subtree"):
+        taffylib.node_replace_subtree(taffy_ptr, children[0], parent)
+    with pytest.raises(KeyError):
+        taffylib.node_replace_subtree(taffy_ptr, children[0], 999999)
+    assert taffylib.node_children(taffy_ptr, parent) == [children[0], children[2]]
+    taffylib.free(taffy_ptr)
+
+
+def test_node_insert_child_at_index():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    a, b, c, d = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 4)
+    taffylib.node_insert_child_at_index(taffy_ptr, parent, 0, b)
+    taffylib.node_insert_child_at_index(taffy_ptr, parent, 0, a)
+    taffylib.node_insert_child_at_index(taffy_ptr, parent, 2, d)
+    taffylib.node_insert_child_at_index(taffy_ptr, parent, 2, c)
+    assert taffylib.node_children(taffy_ptr, parent) == [a, b, c, d]
+    assert taffylib.node_parent(taffy_ptr, c) == parent
+    other = taffylib.node_create(taffy_ptr, Style().to_dict())
+    with pytest.raises(IndexError):
+        taffylib.node_insert_child_at_index(taffy_ptr, parent, 5, other)
+    assert taffylib.node_children(taffy_ptr, parent) == [a, b, c, d]
+
+    # A child with a parent is moved, and is not inserted twice
+    taffylib.node_add_child(taffy_ptr, other, a)
+    taffylib.node_insert_child_at_index(taffy_ptr, parent, 0, a)
+    assert taffylib.node_children(taffy_ptr, other) == []
+    assert taffylib.node_children(taffy_ptr, parent) == [a, b, c, d]
+    taffylib.node_insert_child_at_index(taffy_ptr, parent, 3, a)
+    assert taffylib.node_children(taffy_ptr, parent) == [b, c, d, a]
+    assert taffylib.node_parent(taffy_ptr, a) == parent
+
+    # A node cannot be inserted into itself or its own subtree
+    taffylib.node_add_child(taffy_ptr, other, parent)
+    for node in (parent, b):
+        with pytest.raises(ValueError, match="own subtree"):
+            taffylib.node_insert_child_at_index(taffy_ptr, node, 0, other)
+    with pytest.raises(ValueError, match="own subtree"):
+        taffylib.node_insert_child_at_index(taffy_ptr, other, 0, other)
+    assert taffylib.node_children(taffy_ptr, parent) == [b, c, d, a]
+    assert taffylib.node_parent(taffy_ptr, other) is None
+    taffylib.free(taffy_ptr)
+
+
+def test_node_swap_children():
+    taffy_ptr = taffylib.init()
+    parent = taffylib.node_create(taffy_ptr, Style().to_dict())
+    a, b, c, d = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 4)
+    taffylib.node_set_children(taffy_ptr, parent, [a, b, c, d])
+    taffylib.node_compute_layout(taffy_ptr, parent, Size(100 * PT, 100 * PT).to_dict())
+    taffylib.node_swap_children(taffy_ptr, parent, 0, 3)
+    assert taffylib.node_children(taffy_ptr, parent) == [d, b, c, a]
+    assert all(taffylib.node_parent(taffy_ptr, child) == parent for child in (a, d))
+    assert taffylib.node_dirty(taffy_ptr, parent)
+    taffylib.node_swap_children(taffy_ptr, parent, 1, 1)
+    assert taffylib.node_children(taffy_ptr, parent) == [d, b, c, a]
+    with pytest.raises(IndexError):
+        taffylib.node_swap_children(taffy_ptr, parent, 0, 4)
+    assert taffylib.node_children(taffy_ptr, parent) == [d, b, c, a]
+    taffylib.free(taffy_ptr)
+
+
+def test_set_direction():
+    def row(rtl):
+        taffy_ptr = taffylib.init()
+        taffylib.set_direction(taffy_ptr, rtl)
+        root = taffylib.node_create(
+            taffy_ptr, Style(size=Size(100 * PT, 20 * PT), padding=2 * PT).to_dict()
+        )
+        children = taffylib.nodes_create(
+            taffy_ptr, [Style(size=Size(w * PT, 10 * PT)).to_dict() for w in (20, 30)]
+        )
+        taffylib.node_set_children(taffy_ptr, root, children)
+        taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 20 * PT).to_dict())
+        layouts = [taffylib.node_get_layout(taffy_ptr, child) for child in children]
+        taffylib.free(taffy_ptr)
+        return layouts
+
+    ltr, rtl = row(False), row(True)
+    assert [layout["left"] for layout in ltr] == [2, 22]
+    for a, b in zip(ltr, rtl):
+        assert b["left"] == 100 - a["left"] - a["width"]
+        assert (b["top"], b["width"], b["height"]) == (a["top"], a["width"], a["height"])
+
+
+def test_set_direction_swaps_left_right():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(size=Size(100 * PT, 20 * PT)).to_dict())
+    style = Style(size=Size(20 * PT, 10 * PT), margin=Rect(0, 0, 0, 5 * PT)).to_dict()
+    first = taffylib.node_create(taffy_ptr, style)
+    second = taffylib.node_create(taffy_ptr, Style(size=Size(30 * PT, 10 * PT)).to_dict())
+    taffylib.node_set_children(taffy_ptr, root, [first, second])
+    available_space = Size(100 * PT, 20 * PT).to_dict()
+
+    # The margin stays on the left of the first item, between it and the second
+    taffylib.set_direction(taffy_ptr, True)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert taffylib.node_get_layout(taffy_ptr, first)["left"] == 80
+    assert taffylib.node_get_layout(taffy_ptr, second)["left"] == 45
+    assert _same(taffylib.node_get_style(taffy_ptr, first), style)
+
+    taffylib.set_direction(taffy_ptr, False)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert taffylib.node_get_layout(taffy_ptr, first)["left"] == 5
+    assert taffylib.node_get_layout(taffy_ptr, second)["left"] == 25
+    assert _same(taffylib.node_get_style(taffy_ptr, first), style)
+    taffylib.free(taffy_ptr)
+
+
+def _grid_tree(taffy_ptr):
+    root = taffylib.node_create(
+        taffy_ptr,
+        Style(
+            display=Display.GRID,
+            size=Size(200 * PT, 100 * PT),
+            grid_template_columns=["repeat(2,50px)", "1fr"],
+            grid_template_rows=["40px", "auto"],
+        ).to_dict(),
+    )
+    children = taffylib.nodes_create(
+        taffy_ptr,
+        [Style(margin=i * PT, flex_grow=i).to_dict() for i in range(1, 4)],
+    )
+    taffylib.node_set_children(taffy_ptr, root, children)
+    return root
+
+
+def test_tree_to_json():
+    taffy_ptr = taffylib.init()
+    root = _grid_tree(taffy_ptr)
+    dumped = taffylib.tree_to_json(taffy_ptr, root)
+    assert taffylib.tree_to_json(taffy_ptr, root) == dumped
+
+    tree = json.loads(dumped)
+    assert tree["style"]["display"] == Display.GRID
+    assert tree["style"]["size"]["width"] == dict(dim=1, value=200)
+    columns = tree["style"]["grid_template_columns"]
+    assert [column["repetition"] for column in columns] == [2, -2]
+    assert columns[0]["repeat"][0]["max_size"] == dict(dim=1, value=50)
+    assert columns[1]["single"]["max_size"] == dict(dim=7, value=1)
+    assert len(tree["children"]) == 3
+    for i, child in enumerate(tree["children"], start=1):
+        assert child["style"]["flex_grow"] == i
+        assert child["style"]["margin"]["left"] == dict(dim=1, value=i)
+        assert child["children"] == []
+
+    # Identical trees give identical output
+    other_ptr = taffylib.init()
+    assert taffylib.tree_to_json(other_ptr, _grid_tree(other_ptr)) == dumped
+    taffylib.free(other_ptr)
+    taffylib.free(taffy_ptr)
+
+
+def test_tree_to_json_strict():
+    def parse_constant(name):
+        raise ValueError(f"{name} is not valid JSON")
+
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    dumped = taffylib.tree_to_json(taffy_ptr, root)
+    # Lengths without a value (eg. auto) are written as null, not NaN
+    tree = json.loads(dumped, parse_constant=parse_constant)
+    assert tree["style"]["size"]["width"] == dict(dim=0, value=None)
+
+    # and read back as NaN
+    copy = taffylib.tree_from_json(taffy_ptr, dumped)
+    assert _same(
+        taffylib.node_get_style(taffy_ptr, copy),
+        taffylib.node_get_style(taffy_ptr, root),
+    )
+    assert taffylib.tree_to_json(taffy_ptr, copy) == dumped
+    taffylib.free(taffy_ptr)
+
+
+def test_tree_from_json():
+    taffy_ptr = taffylib.init()
+    dumped = taffylib.tree_to_json(taffy_ptr, _grid_tree(taffy_ptr))
+    taffylib.node_drop_all(taffy_ptr)
+    assert taffylib.total_node_count(taffy_ptr) == 0
+
+    root = taffylib.tree_from_json(taffy_ptr, dumped)
+    assert taffylib.total_node_count(taffy_ptr) == 4
+    assert taffylib.node_parent(taffy_ptr, root) is None
+    children = taffylib.node_children(taffy_ptr, root)
+    assert len(children) == 3
+    styles = [taffylib.node_get_style(taffy_ptr, child) for child in children]
+    assert [style["flex_grow"] for style in styles] == [1, 2, 3]
+    assert taffylib.tree_to_json(taffy_ptr, root) == dumped
+
+    with pytest.raises(ValueError):
+        taffylib.tree_from_json(taffy_ptr, "{not json")
+    with pytest.raises(ValueError, match="invalid tree"):
+        taffylib.tree_from_json(taffy_ptr, '{"style": {}, "children": []}')
+    # No nodes are created if any of the styles is invalid
+    invalid = json.loads(dumped)
+    invalid["children"][2]["style"]["display"] = 9
+    with pytest.raises(ValueError, match="invalid index 9"):
+        taffylib.tree_from_json(taffy_ptr, json.dumps(invalid))
+    assert taffylib.total_node_count(taffy_ptr) == 4
+    taffylib.free(taffy_ptr)
+
+
+def test_tree_to_json_subtree():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(padding=5 * PT).to_dict())
+    component = _grid_tree(taffy_ptr)
+    taffylib.node_set_children(
+        taffy_ptr, root, [taffylib.node_create(taffy_ptr, Style().to_dict()), component]
+    )
+    # Only the node and its descendants are included
+    dumped = taffylib.tree_to_json(taffy_ptr, component)
+    assert len(json.loads(dumped)["children"]) == 3
+
+    # The subtree is re-imported as a standalone tree
+    other_ptr = taffylib.init()
+    copy = taffylib.tree_from_json(other_ptr, dumped)
+    assert taffylib.node_parent(other_ptr, copy) is None
+    assert taffylib.total_node_count(other_ptr) == 4
+    assert taffylib.tree_to_json(other_ptr, copy) == dumped
+    available_space = Size(300 * PT, 300 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, component, available_space)
+    taffylib.node_compute_layout(other_ptr, copy, available_space)
+    layouts = taffylib.subtree_get_layouts(taffy_ptr, component)
+    copy_layouts = taffylib.subtree_get_layouts(other_ptr, copy)
+    assert [layout for _, layout in copy_layouts] == [layout for _, layout in layouts]
+    taffylib.free(other_ptr)
+    taffylib.free(taffy_ptr)
+
+
+def test_set_rounding_scale():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(size=Size(100 * PT, 10 * PT)).to_dict())
+    children = taffylib.nodes_create(taffy_ptr, [Style(flex_grow=1).to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    taffylib.enable_rounding(taffy_ptr)
+    taffylib.set_rounding_scale(taffy_ptr, 2.0)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 10 * PT).to_dict())
+    layouts = [taffylib.node_get_layout(taffy_ptr, child) for child in children]
+    assert [layout["left"] for layout in layouts] == [0, 33.5, 66.5]
+    assert [layout["width"] for layout in layouts] == [33.5, 33, 33.5]
+    for layout in layouts:
+        for key in ("left", "top", "width", "height"):
+            assert (layout[key] * 2).is_integer()
+
+    for scale in (0, -1, NAN):
+        with pytest.raises(ValueError):
+            taffylib.set_rounding_scale(taffy_ptr, scale)
+    taffylib.free(taffy_ptr)
+
+
+def test_round_layout():
+    taffy_ptr = taffylib.init()
+    style = Style(size=Size(100 * PT, 10 * PT))
+    root = taffylib.node_create(taffy_ptr, style.to_dict())
+    children = taffylib.nodes_create(taffy_ptr, [Style(flex_grow=1).to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    style = Style(margin=Rect(left=0.3 * PT, right=0, top=0, bottom=0), flex_grow=1)
+    grandchild = taffylib.node_create(taffy_ptr, style.to_dict())
+    taffylib.node_add_child(taffy_ptr, children[1], grandchild)
+    taffylib.enable_rounding(taffy_ptr)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 10 * PT).to_dict())
+
+    # Matches the layouts rounded by the tree, given the unrounded location of the
+    # parent (the root is at 0, 0)
+    for node in [root, *children]:
+        unrounded = taffylib.node_get_unrounded_layout(taffy_ptr, node)
+        expected = taffylib.node_get_layout(taffy_ptr, node)
+        assert taffylib.round_layout(unrounded) == expected
+    parent_x = taffylib.node_get_unrounded_layout(taffy_ptr, children[1])["left"]
+    unrounded = taffylib.node_get_unrounded_layout(taffy_ptr, grandchild)
+    expected = taffylib.node_get_layout(taffy_ptr, grandchild)
+    assert unrounded != expected
+    assert taffylib.round_layout(unrounded, parent_x=parent_x) == expected
+    # Without the location of the parent, the size may be rounded differently
+    assert expected["width"] == 33
+    assert taffylib.round_layout(unrounded)["width"] == 34
+
+    assert taffylib.round_layout(unrounded, 2.0)["left"] == 0.5
+    with pytest.raises(ValueError):
+        taffylib.round_layout(unrounded, 0)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_depth():
+    taffy_ptr = taffylib.init()
+    nodes = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 5)
+    for parent, child in zip(nodes[:3], nodes[1:4]):
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    taffylib.node_add_child(taffy_ptr, nodes[1], nodes[4])
+    assert [taffylib.node_depth(taffy_ptr, node) for node in nodes] == [0, 1, 2, 3, 2]
+    taffylib.node_remove_child(taffy_ptr, nodes[0], nodes[1])
+    assert taffylib.node_depth(taffy_ptr, nodes[3]) == 2
+    taffylib.free(taffy_ptr)
+
+
+def test_subtree_dirty_count():
+    taffy_ptr = taffylib.init()
+    root, first, second = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    leaves = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, root, [first, second])
+    taffylib.node_set_children(taffy_ptr, first, leaves[:2])
+    taffylib.node_add_child(taffy_ptr, second, leaves[2])
+    assert taffylib.subtree_dirty_count(taffy_ptr, root) == 6
+
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+    assert taffylib.subtree_dirty_count(taffy_ptr, root) == 0
+
+    # Marking a node dirty marks its ancestors as well
+    taffylib.node_mark_dirty(taffy_ptr, leaves[0])
+    taffylib.node_mark_dirty(taffy_ptr, leaves[1])
+    assert taffylib.subtree_dirty_count(taffy_ptr, root) == 4
+    assert taffylib.subtree_dirty_count(taffy_ptr, first) == 3
+    assert taffylib.subtree_dirty_count(taffy_ptr, second) == 0
+    taffylib.free(taffy_ptr)
+
+
+def test_subtree_leaf_count():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    assert taffylib.subtree_leaf_count(taffy_ptr, root) == 1
+
+    # 3 rows of 4 cells each, and an empty row
+    rows = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 4)
+    taffylib.node_set_children(taffy_ptr, root, rows)
+    for row in rows[:3]:
+        cells = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 4)
+        taffylib.node_set_children(taffy_ptr, row, cells)
+    assert taffylib.subtree_leaf_count(taffy_ptr, root) == 13
+    assert taffylib.subtree_leaf_count(taffy_ptr, rows[0]) == 4
+    assert taffylib.total_node_count(taffy_ptr) == 17
+    with pytest.raises(KeyError):
+        taffylib.subtree_leaf_count(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize("display", [Display.FLEX, Display.GRID])
+def test_subtree_paint_order(display):
+    taffy_ptr = taffylib.init()
+    style = Style(display=display, size=Size(100 * PT, 100 * PT))
+    root = taffylib.node_create(taffy_ptr, style.to_dict())
+    style = Style(position=Position.ABSOLUTE, inset=Rect(0 * PT))
+    overlay = taffylib.node_create(taffy_ptr, style.to_dict())
+    first, second, grandchild = taffylib.nodes_create(
+        taffy_ptr, [Style(size=Size(10 * PT, 10 * PT)).to_dict()] * 3
+    )
+    taffylib.node_set_children(taffy_ptr, root, [first, overlay, second])
+    taffylib.node_add_child(taffy_ptr, first, grandchild)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+
+    # The overlay is painted after its siblings (and their descendants)
+    order = [root, first, grandchild, second, overlay]
+    assert taffylib.subtree_paint_order(taffy_ptr, root) == order
+    assert taffylib.subtree_paint_order(taffy_ptr, first) == [first, grandchild]
+    # Also when it is the first child, and hit testing agrees with the paint order
+    taffylib.node_set_children(taffy_ptr, root, [overlay, first, second])
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+    assert taffylib.subtree_paint_order(taffy_ptr, root) == order
+    assert taffylib.node_at_point(taffy_ptr, root, 5, 5) == overlay
+    with pytest.raises(KeyError):
+        taffylib.subtree_paint_order(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_dirty_recursive():
+    taffy_ptr = taffylib.init()
+    chain = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 5)
+    for parent, child in zip(chain, chain[1:]):
+        taffylib.node_add_child(taffy_ptr, parent, child)
+    sibling = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, chain[0], sibling)
+    assert taffylib.node_dirty_recursive(taffy_ptr, chain[0])
+
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, chain[0], available_space)
+    assert not any(taffylib.node_dirty_recursive(taffy_ptr, n) for n in chain)
+
+    # A dirty descendant makes all its ancestors report dirty
+    taffylib.node_mark_dirty(taffy_ptr, chain[-1])
+    assert all(taffylib.node_dirty_recursive(taffy_ptr, n) for n in chain)
+    assert not taffylib.node_dirty_recursive(taffy_ptr, sibling)
+    taffylib.node_compute_layout(taffy_ptr, chain[0], available_space)
+
+    # Taffy does not lay out the descendants of hidden nodes, so they can stay
+    # dirty, but that does not make their ancestors dirty
+    taffylib.node_set_style(taffy_ptr, chain[1], Style(display=Display.NONE).to_dict())
+    hidden = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, chain[2], hidden)
+    taffylib.node_compute_layout(taffy_ptr, chain[0], available_space)
+    assert taffylib.node_dirty(taffy_ptr, hidden)
+    assert not taffylib.node_dirty(taffy_ptr, chain[0])
+    assert not taffylib.node_dirty_recursive(taffy_ptr, chain[0])
+    taffylib.free(taffy_ptr)
+
+
+def test_compute_layout_releases_gil():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(flex_wrap=FlexWrap.WRAP).to_dict())
+    for _ in range(100):
+        row = taffylib.node_create(taffy_ptr, Style(flex_grow=1).to_dict())
+        children = taffylib.nodes_create(
+            taffy_ptr, [Style(flex_grow=1, padding=1 * PT).to_dict()] * 100
+        )
+        taffylib.node_set_children(taffy_ptr, row, children)
+        taffylib.node_add_child(taffy_ptr, root, row)
+
+    counter = 0
+    errors = []
+    stop = threading.Event()
+
+    def count():
+        nonlocal counter
+        while not stop.is_set():
+            counter += 1
+            # Waits for the layout computation to finish, rather than failing
+            try:
+                taffylib.total_node_count(taffy_ptr)
+            except Exception as e:
+                errors.append(e)
+            time.sleep(0.0001)
+
+    # Prevent switching threads other than when the GIL is released explicitly
+    interval = sys.getswitchinterval()
+    sys.setswitchinterval(1000)
+    thread = threading.Thread(target=count)
+    thread.start()
+    try:
+        before = counter
+        taffylib.node_compute_layout(
+            taffy_ptr, root, Size(1000 * PT, 1000 * PT).to_dict()
+        )
+        during = counter - before
+    finally:
+        stop.set()
+        thread.join()
+        sys.setswitchinterval(interval)
+    assert during > 0
+    assert not errors
+    taffylib.free(taffy_ptr)
+
+
+def test_concurrent_reads_and_writes():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(flex_wrap=FlexWrap.WRAP).to_dict())
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 1000)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    available_space = Size(1000 * PT, 1000 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+
+    errors = []
+    stop = threading.Event()
+
+    def mutate():
+        try:
+            for i in range(50):
+                style = Style(size=Size((10 + i % 2) * PT, 10 * PT)).to_dict()
+                taffylib.nodes_set_styles(taffy_ptr, [(c, style) for c in children])
+                taffylib.node_compute_layout(taffy_ptr, root, available_space)
+        except Exception as e:
+            errors.append(e)
+        finally:
+            stop.set()
+
+    def read():
+        try:
+            while not stop.is_set():
+                # The layout is computed while the tree is locked for writing,
+                # so all children always have the same width when read
+                layouts = taffylib.subtree_get_layouts(taffy_ptr, root)
+                widths = {layout["width"] for _, layout in layouts[1:]}
+                assert len(widths) == 1
+                taffylib.node_get_style(taffy_ptr, children[-1])
+        except Exception as e:
+            errors.append(e)
+
+    threads = [threading.Thread(target=read) for _ in range(4)]
+    threads.append(threading.Thread(target=mutate))
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+    assert not errors
+    taffylib.free(taffy_ptr)
+
+
+def test_measure_reentrant():
+    taffy_ptr = taffylib.init()
+    other_ptr = taffylib.init()
+    other = taffylib.node_create(other_ptr, Style().to_dict())
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+
+    def measure_other(
+        context, known_width, known_height, available_width, available_height
+    ):
+        # Other trees can be used from a `measure` function
+        taffylib.node_compute_layout(other_ptr, other, available_space)
+        return taffylib.node_get_size(other_ptr, other)
+
+    taffylib.node_set_measure(taffy_ptr, node, None, measure_other)
+    taffylib.node_compute_layout(taffy_ptr, node, available_space, strict=True)
+
+    def measure_same(
+        context, known_width, known_height, available_width, available_height
+    ):
+        # ... but not the tree being computed
+        taffylib.node_get_size(taffy_ptr, node)
+        return (0.0, 0.0)
+
+    taffylib.node_set_measure(taffy_ptr, node, None, measure_same)
+    with pytest.raises(RuntimeError, match="taffy is busy"):
+        taffylib.node_compute_layout(taffy_ptr, node, available_space, strict=True)
+    taffylib.free(taffy_ptr)
+    taffylib.free(other_ptr)
+
+
+def test_node_compute_layout_errors():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, root, child)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    assert taffylib.node_compute_layout(taffy_ptr, root, available_space) is True
+
+    taffylib.node_drop(taffy_ptr, root)
+    with pytest.raises(KeyError) as exc_info:
+        taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert exc_info.value.args == (root,)
+    with pytest.raises(KeyError):
+        taffylib.compute_layouts(taffy_ptr, [(root, available_space)])
+    # The detached child can still be computed as a root
+    assert taffylib.node_compute_layout(taffy_ptr, child, available_space) is True
+    taffylib.free(taffy_ptr)
+
+
+def test_node_intrinsic_size():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(flex_wrap=FlexWrap.WRAP).to_dict())
+    text = taffylib.node_create(taffy_ptr, Style().to_dict())
+    box = taffylib.node_create(taffy_ptr, Style(size=Size(20 * PT, 10 * PT)).to_dict())
+    taffylib.node_set_children(taffy_ptr, root, [text, box])
+
+    # Six words of 10 x 10 points each, wrapped to fit the available width
+    def measure_text(
+        context, known_width, known_height, available_width, available_height
+    ):
+        match available_width["dim"]:
+            case 3:
+                per_line = 1
+            case 4:
+                per_line = 6
+            case _:
+                per_line = max(1, int(available_width["value"] // 10))
+        return (min(6, per_line) * 10.0, -(-6 // per_line) * 10.0)
+
+    taffylib.node_set_measure(taffy_ptr, text, None, measure_text)
+    available_space = Size(35 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    layouts = taffylib.subtree_get_layouts(taffy_ptr, root)
+
+    min_width, max_width = taffylib.node_intrinsic_size(taffy_ptr, root)
+    assert (min_width, max_width) == (20, 80)
+    assert max_width >= min_width
+    assert taffylib.node_intrinsic_size(taffy_ptr, text) == (10, 60)
+    # The current layout is not changed
+    assert taffylib.subtree_get_layouts(taffy_ptr, root) == layouts
+    assert not taffylib.node_dirty(taffy_ptr, root)
+    taffylib.node_set_style(
+        taffy_ptr, box, Style(size=Size(40 * PT, 10 * PT)).to_dict()
+    )
+    assert taffylib.node_intrinsic_size(taffy_ptr, root) == (40, 100)
+    assert taffylib.node_dirty(taffy_ptr, root)
+    assert taffylib.subtree_get_layouts(taffy_ptr, root) == layouts
+    with pytest.raises(KeyError):
+        taffylib.node_intrinsic_size(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_measure():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style(padding=5 * PT).to_dict())
+    text = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_set_children(taffy_ptr, root, [text])
+
+    # Six words of 10 x 10 points each, wrapped to fit the available width
+    def measure_text(
+        context, known_width, known_height, available_width, available_height
+    ):
+        match available_width["dim"]:
+            case 3:
+                per_line = 1
+            case 4:
+                per_line = 6
+            case _:
+                per_line = max(1, int(available_width["value"] // 10))
+        return (min(6, per_line) * 10.0, -(-6 // per_line) * 10.0)
+
+    taffylib.node_set_measure(taffy_ptr, text, None, measure_text)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    layouts = taffylib.subtree_get_layouts(taffy_ptr, root)
+
+    # A definite width and a max-content height
+    width, height = (30 * PT).to_dict(), MAX_CONTENT.to_dict()
+    assert taffylib.node_measure(taffy_ptr, root, width, height) == (30, 40)
+    assert taffylib.node_measure(taffy_ptr, text, width, height) == (30, 20)
+    min_content = MIN_CONTENT.to_dict()
+    assert taffylib.node_measure(taffy_ptr, root, min_content, height) == (20, 70)
+    # The current layout is not changed
+    assert taffylib.subtree_get_layouts(taffy_ptr, root) == layouts
+    assert not taffylib.node_dirty(taffy_ptr, root)
+    with pytest.raises(KeyError):
+        taffylib.node_measure(taffy_ptr, 999999, width, height)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_measure_changed():
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+    width = 10.0
+
+    def measure(context, known_width, known_height, available_width, available_height):
+        return (width, 5.0)
+
+    taffylib.node_set_measure(taffy_ptr, node, None, measure)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, node, available_space)
+    assert taffylib.node_get_layout(taffy_ptr, node)["width"] == 10
+
+    # The results of the measure function are not kept from the last layout
+    width = 40.0
+    taffylib.node_mark_dirty(taffy_ptr, node)
+    length = (100 * PT).to_dict()
+    assert taffylib.node_measure(taffy_ptr, node, length, length) == (40, 5)
+    assert taffylib.node_intrinsic_size(taffy_ptr, node) == (40, 40)
+    taffylib.free(taffy_ptr)
+
+
+def test_profiling():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_set_children(
+        taffy_ptr, root, taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    )
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+
+    records = []
+    handler = logging.Handler()
+    handler.emit = records.append
+    logger = logging.getLogger("stretchable.taffylib")
+    level = logger.level
+    logger.addHandler(handler)
+    logger.setLevel(logging.DEBUG)
+    try:
+        # Off by default
+        taffylib.node_compute_layout(taffy_ptr, root, available_space)
+        assert not records
+
+        taffylib.set_profiling(True)
+        taffylib.node_mark_dirty(taffy_ptr, root)
+        taffylib.node_compute_layout(taffy_ptr, root, available_space)
+        assert len(records) == 1
+        assert records[0].levelno == logging.DEBUG
+        fields = dict(
+            field.split("=") for field in records[0].getMessage().split()[1:]
+        )
+        assert fields["node"] == str(root)
+        assert fields["nodes"] == "4"
+        assert float(fields["duration_ms"]) >= 0
+
+        taffylib.set_profiling(False)
+        taffylib.node_compute_layout(taffy_ptr, root, available_space)
+        assert len(records) == 1
+    finally:
+        taffylib.set_profiling(False)
+        logger.removeHandler(handler)
+        logger.setLevel(level)
+        taffylib.free(taffy_ptr)
+
+
+def test_compute_layouts():
+    taffy_ptr = taffylib.init()
+    style = Style(size=Size(100 * PCT, 100 * PCT), padding=10 * PT).to_dict()
+    roots = taffylib.nodes_create(taffy_ptr, [style] * 2)
+    for root in roots:
+        child = taffylib.node_create(taffy_ptr, Style(flex_grow=1).to_dict())
+        taffylib.node_add_child(taffy_ptr, root, child)
+    sizes = [Size(100 * PT, 50 * PT), Size(300 * PT, 80 * PT)]
+    results = taffylib.compute_layouts(
+        taffy_ptr, [(root, size.to_dict()) for root, size in zip(roots, sizes)]
+    )
+    assert results == [True, True]
+    for root, (width, height) in zip(roots, [(100, 50), (300, 80)]):
+        assert not taffylib.node_dirty(taffy_ptr, root)
+        (child,) = taffylib.node_children(taffy_ptr, root)
+        layout = taffylib.node_get_layout(taffy_ptr, child)
+        assert (layout["width"], layout["height"]) == (width - 20, height - 20)
+
+    assert taffylib.compute_layouts(taffy_ptr, []) == []
+    with pytest.raises(KeyError):
+        taffylib.compute_layouts(taffy_ptr, [(999999, sizes[0].to_dict())])
+
+    # A failed layout does not stop the other roots from being computed
+    def measure(node, known_width, known_height, available_width, available_height):
+        raise ValueError("measure failed")
+
+    (child,) = taffylib.node_children(taffy_ptr, roots[0])
+    taffylib.node_set_measure(taffy_ptr, child, child, measure)
+    for root in roots:
+        taffylib.node_mark_dirty(taffy_ptr, root)
+    roots_sizes = [(root, size.to_dict()) for root, size in zip(roots, sizes)]
+    results = taffylib.compute_layouts(taffy_ptr, roots_sizes, strict=True)
+    assert results == [False, True]
+    assert taffylib.node_dirty(taffy_ptr, roots[0])
+    assert not taffylib.node_dirty(taffy_ptr, roots[1])
+    assert taffylib.compute_layouts(taffy_ptr, roots_sizes) == [True, True]
+    taffylib.free(taffy_ptr)
+
+
+def test_node_compute_layout_diff():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PCT, 100 * PCT), padding=10 * PT).to_dict()
+    )
+    children = taffylib.nodes_create(taffy_ptr, [Style(flex_grow=1).to_dict()] * 2)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    available_space = Size(200 * PT, 100 * PT).to_dict()
+    assert taffylib.node_compute_layout_diff(taffy_ptr, root, available_space)
+    assert not taffylib.node_compute_layout_diff(taffy_ptr, root, available_space)
+
+    # Recomputing a dirty but otherwise unchanged tree
+    taffylib.node_mark_dirty(taffy_ptr, children[0])
+    assert not taffylib.node_compute_layout_diff(taffy_ptr, root, available_space)
+
+    # Only the second child moves
+    taffylib.node_set_style(taffy_ptr, children[1], Style(flex_grow=2).to_dict())
+    assert taffylib.node_compute_layout_diff(taffy_ptr, root, available_space)
+    assert taffylib.node_get_layout(taffy_ptr, children[1])["width"] == 120
+
+    available_space = Size(300 * PT, 100 * PT).to_dict()
+    assert taffylib.node_compute_layout_diff(taffy_ptr, root, available_space)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_is_overflowing():
+    taffy_ptr = taffylib.init()
+    container = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    child = taffylib.node_create(
+        taffy_ptr, Style(size=Size(150 * PT, 50 * PT), flex_shrink=0).to_dict()
+    )
+    taffylib.node_add_child(taffy_ptr, container, child)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, container, available_space)
+    assert taffylib.node_is_overflowing(taffy_ptr, container) == (True, False)
+    assert taffylib.node_is_overflowing(taffy_ptr, child) == (False, False)
+
+    taffylib.node_set_style(
+        taffy_ptr, child, Style(size=Size(50 * PT, 150 * PT), flex_shrink=0).to_dict()
+    )
+    taffylib.node_compute_layout(taffy_ptr, container, available_space)
+    assert taffylib.node_is_overflowing(taffy_ptr, container) == (False, True)
+
+    taffylib.node_set_style(
+        taffy_ptr, child, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    taffylib.node_compute_layout(taffy_ptr, container, available_space)
+    assert taffylib.node_is_overflowing(taffy_ptr, container) == (False, False)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_scroll_extent():
+    taffy_ptr = taffylib.init()
+    container = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    child = taffylib.node_create(
+        taffy_ptr, Style(size=Size(50 * PT, 50 * PT)).to_dict()
+    )
+    taffylib.node_add_child(taffy_ptr, container, child)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, container, available_space)
+    # The children are within the box of the container
+    assert taffylib.node_scroll_extent(taffy_ptr, container) == (100, 100)
+
+    # A child positioned past the bounds of the container, with a descendant that
+    # extends even further
+    positioned = taffylib.node_create(
+        taffy_ptr,
+        Style(
+            position=Position.ABSOLUTE,
+            inset=Rect(top=120 * PT, left=80 * PT, bottom=AUTO, right=AUTO),
+            size=Size(40 * PT, 30 * PT),
+        ).to_dict(),
+    )
+    grandchild = taffylib.node_create(
+        taffy_ptr,
+        Style(size=Size(10 * PT, 60 * PT), flex_shrink=0).to_dict(),
+    )
+    taffylib.node_add_child(taffy_ptr, positioned, grandchild)
+    taffylib.node_add_child(taffy_ptr, container, positioned)
+    taffylib.node_compute_layout(taffy_ptr, container, available_space)
+    assert taffylib.node_scroll_extent(taffy_ptr, container) == (120, 180)
+    assert taffylib.node_scroll_extent(taffy_ptr, positioned) == (40, 60)
+    assert taffylib.node_scroll_extent(taffy_ptr, grandchild) == (10, 60)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_scroll_padding():
+    taffy_ptr = taffylib.init()
+    # A scrollable list of 10 rows, 20 points each
+    style = Style(flex_direction=FlexDirection.COLUMN, size=Size(50 * PT, 50 * PT))
+    scroller = taffylib.node_create(taffy_ptr, style.to_dict())
+    rows = taffylib.nodes_create(
+        taffy_ptr, [Style(size=Size(60 * PT, 20 * PT), flex_shrink=0).to_dict()] * 10
+    )
+    taffylib.node_set_children(taffy_ptr, scroller, rows)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, scroller, available_space)
+    assert taffylib.node_scroll_extent(taffy_ptr, scroller) == (60, 200)
+    assert taffylib.node_get_scroll_padding(taffy_ptr, scroller) is None
+
+    padding = Rect(top=5 * PT, right=50 * PCT, bottom=15 * PT, left=10 * PT).to_dict()
+    taffylib.node_set_scroll_padding(taffy_ptr, scroller, padding)
+    assert taffylib.node_get_scroll_padding(taffy_ptr, scroller) == padding
+    # The extent grows by the padding on both axes
+    assert taffylib.node_scroll_extent(taffy_ptr, scroller) == (95, 220)
+    layout = taffylib.node_get_absolute_layout(taffy_ptr, rows[1])
+    assert (layout["left"], layout["top"]) == (10, 25)
+    assert taffylib.node_get_layout(taffy_ptr, rows[1])["top"] == 20
+    taffylib.node_set_scroll_offset(taffy_ptr, scroller, 0, 25)
+    assert taffylib.node_at_point(taffy_ptr, scroller, 20, 0) == rows[1]
+
+    with pytest.raises(ValueError):
+        padding = Rect(-5 * PT).to_dict()
+        taffylib.node_set_scroll_padding(taffy_ptr, scroller, padding)
+    taffylib.node_set_scroll_padding(taffy_ptr, scroller, None)
+    assert taffylib.node_scroll_extent(taffy_ptr, scroller) == (60, 200)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_compute_layout_if_dirty():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, root, child)
+
+    measured = []
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        measured.append(node)
+        return (10.25, 10)
+
+    taffylib.node_set_measure(taffy_ptr, child, child, measure)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert measured
+    measured.clear()
+    assert not taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert not measured
+
+    # Computed again if the available space changed
+    available_space = Size(200 * PT, 100 * PT).to_dict()
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert not taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+
+    # ... if a node in the tree changed
+    taffylib.node_mark_dirty(taffy_ptr, child)
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert not taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+
+    # ... if rounding was disabled or enabled
+    taffylib.disable_rounding(taffy_ptr)
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert not taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+
+    # ... or if the rounding scale changed
+    taffylib.enable_rounding(taffy_ptr)
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert taffylib.node_get_layout(taffy_ptr, child)["width"] == 10
+    taffylib.set_rounding_scale(taffy_ptr, 2.0)
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+    assert taffylib.node_get_layout(taffy_ptr, child)["width"] == 10.5
+    assert not taffylib.node_compute_layout_if_dirty(taffy_ptr, root, available_space)
+
+    # Layouts computed with another root are not reused
+    assert taffylib.node_compute_layout_if_dirty(taffy_ptr, child, available_space)
+    taffylib.free(taffy_ptr)
+
+
+def test_compute_layout_reuses_measurements():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    texts = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 3)
+    taffylib.node_set_children(taffy_ptr, root, texts)
+    calls = []
+
+    def measure(context, known_width, known_height, available_width, available_height):
+        calls.append(context)
+        return (50.0, 10.0)
+
+    for text in texts:
+        taffylib.node_set_measure(taffy_ptr, text, None, measure)
+
+    def compute(width, height):
+        calls.clear()
+        available_space = Size(width * PT, height * PT).to_dict()
+        taffylib.node_compute_layout(taffy_ptr, root, available_space)
+        return len(calls)
+
+    initial = compute(100, 100)
+    # Only the height changed, so the cached sizes of the texts are reused
+    assert compute(100, 200) < initial
+    assert not taffylib.node_dirty(taffy_ptr, texts[0])
+    assert taffylib.node_get_layout(taffy_ptr, texts[2])["width"] == 50
+    # Nothing changed
+    assert compute(100, 200) == 0
+    taffylib.free(taffy_ptr)
+
+
+def test_node_last_available_space():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    child = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, root, child)
+    with pytest.raises(ValueError):
+        taffylib.node_last_available_space(taffy_ptr, root)
+
+    available_space = Size(100 * PT, 50 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert taffylib.node_last_available_space(taffy_ptr, root) == available_space
+
+    # Reflects the most recent layout
+    taffylib.node_compute_layout(taffy_ptr, root, Size(MIN_CONTENT, 80 * PT).to_dict())
+    available_space = taffylib.node_last_available_space(taffy_ptr, root)
+    assert available_space["width"]["dim"] == 3
+    assert available_space["height"] == {"dim": 1, "value": 80}
+
+    # Only roots of a layout have an available space
+    with pytest.raises(ValueError):
+        taffylib.node_last_available_space(taffy_ptr, child)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_get_order():
+    taffy_ptr = taffylib.init()
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    overlay = Style(
+        position=Position.ABSOLUTE,
+        inset=Rect(0 * PT),
+        size=Size(50 * PT, 50 * PT),
+    )
+    item = Style(size=Size(50 * PT, 50 * PT))
+
+    # In a flexbox container, the order is the index of the child
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    children = taffylib.nodes_create(
+        taffy_ptr, [overlay.to_dict(), item.to_dict(), item.to_dict()]
+    )
+    taffylib.node_set_children(taffy_ptr, root, children)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    orders = [taffylib.node_get_order(taffy_ptr, child) for child in children]
+    assert orders == [0, 1, 2]
+    assert orders == [
+        taffylib.node_get_layout(taffy_ptr, child)["order"] for child in children
+    ]
+    # The overlay and the first item overlap, and the overlay is painted on top
+    # (see `subtree_paint_order`), even though its order is lower
+    assert taffylib.node_at_point(taffy_ptr, root, 10, 10) == children[0]
+
+    # In a grid container, absolutely positioned children are painted after the
+    # children in the flow, regardless of their index
+    grid = Style(
+        display=Display.GRID,
+        size=Size(100 * PT, 100 * PT),
+        grid_template_columns=[50, 50],
+    )
+    taffylib.node_set_style(taffy_ptr, root, grid.to_dict())
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    orders = [taffylib.node_get_order(taffy_ptr, child) for child in children]
+    assert orders == [2, 0, 1]
+    assert taffylib.node_at_point(taffy_ptr, root, 10, 10) == children[0]
+    assert taffylib.node_get_order(taffy_ptr, root) == 0
+    with pytest.raises(KeyError):
+        taffylib.node_get_order(taffy_ptr, 999999)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_at_point():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT), padding=10 * PT).to_dict()
+    )
+    child = taffylib.node_create(taffy_ptr, Style(size=Size(50 * PT, 50 * PT)).to_dict())
+    grandchild = taffylib.node_create(
+        taffy_ptr, Style(size=Size(20 * PT, 20 * PT)).to_dict()
+    )
+    overlay = taffylib.node_create(
+        taffy_ptr,
+        Style(
+            position=Position.ABSOLUTE,
+            inset=Rect(40 * PT, AUTO, AUTO, 40 * PT),
+            size=Size(30 * PT, 30 * PT),
+        ).to_dict(),
+    )
+    taffylib.node_set_children(taffy_ptr, root, [child, overlay])
+    taffylib.node_add_child(taffy_ptr, child, grandchild)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+
+    assert taffylib.node_at_point(taffy_ptr, root, 15, 15) == grandchild
+    assert taffylib.node_at_point(taffy_ptr, root, 35, 15) == child
+    # The overlay is above the child where they overlap
+    assert taffylib.node_at_point(taffy_ptr, root, 45, 45) == overlay
+    assert taffylib.node_at_point(taffy_ptr, root, 65, 65) == overlay
+    # Background
+    assert taffylib.node_at_point(taffy_ptr, root, 5, 5) == root
+    assert taffylib.node_at_point(taffy_ptr, root, 90, 20) == root
+    # Outside the root
+    assert taffylib.node_at_point(taffy_ptr, root, 100, 50) is None
+    assert taffylib.node_at_point(taffy_ptr, root, -1, 50) is None
+    # Within a subtree
+    assert taffylib.node_at_point(taffy_ptr, child, 15, 15) == grandchild
+    assert taffylib.node_at_point(taffy_ptr, child, 65, 65) is None
+
+    # An absolutely positioned overlay is above its in-flow siblings, also when it
+    # comes first
+    style = Style(position=Position.ABSOLUTE, inset=Rect(0 * PT))
+    cover = taffylib.node_create(taffy_ptr, style.to_dict())
+    taffylib.node_set_children(taffy_ptr, root, [cover, child, overlay])
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+    assert taffylib.node_at_point(taffy_ptr, root, 15, 15) == cover
+    assert taffylib.node_at_point(taffy_ptr, root, 65, 65) == overlay
+    taffylib.free(taffy_ptr)
+
+
+def test_node_compute_layout_strict():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(100 * PT, 100 * PT)).to_dict()
+    )
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    taffylib.node_set_children(taffy_ptr, root, children)
+    calls = []
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        calls.append(node)
+        raise ValueError("measure failed")
+
+    for child in children:
+        taffylib.node_set_measure(taffy_ptr, child, child, measure)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+
+    # By default, errors are logged and the computation completes
+    assert taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert not taffylib.node_dirty(taffy_ptr, root)
+
+    calls.clear()
+    for child in children:
+        taffylib.node_mark_dirty(taffy_ptr, child)
+    with pytest.raises(ValueError, match="measure failed"):
+        taffylib.node_compute_layout(taffy_ptr, root, available_space, strict=True)
+    # Only the first error is raised, remaining measure calls are skipped
+    assert calls == [children[0]]
+    assert taffylib.node_dirty(taffy_ptr, root)
+
+    # Strict mode does not carry over to the next computation
+    assert taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize(
+    "size", [(10.0,), [10.0, 20.0, 30.0], (10.0, "20"), (10.0, float("inf")), 10.0]
+)
+def test_node_set_measure_invalid_size(size):
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        return size
+
+    taffylib.node_set_measure(taffy_ptr, node, node, measure)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    with pytest.raises(TypeError, match="two floats"):
+        taffylib.node_compute_layout(taffy_ptr, node, available_space, strict=True)
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize("size", [(10, 20), [10.0, 20.0], (NAN, 20.0)])
+def test_node_set_measure_valid_size(size):
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        return size
+
+    taffylib.node_set_measure(taffy_ptr, node, node, measure)
+    available_space = Size(100 * PT, 100 * PT).to_dict()
+    assert taffylib.node_compute_layout(taffy_ptr, node, available_space, strict=True)
+    assert taffylib.node_get_layout(taffy_ptr, node)["height"] == 20
+    taffylib.free(taffy_ptr)
+
+
+def test_node_set_measure_known_dimension():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr,
+        Style(flex_direction=FlexDirection.COLUMN, size=Size(100 * PT, AUTO)).to_dict(),
+    )
+    text = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, root, text)
+    text_width, line_height = 250, 10
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        if known_width != known_width:
+            return (text_width, line_height)
+        # Only the height depends on the known width
+        lines = -(-text_width // known_width)
+        return (None, lines * line_height)
+
+    taffylib.node_set_measure(taffy_ptr, text, text, measure)
+    taffylib.node_compute_layout(
+        taffy_ptr, root, Size(1000 * PT, 1000 * PT).to_dict(), strict=True
+    )
+    layout = taffylib.node_get_layout(taffy_ptr, text)
+    assert (layout["width"], layout["height"]) == (100, 30)
+    assert taffylib.node_get_layout(taffy_ptr, root)["height"] == 30
+    taffylib.free(taffy_ptr)
+
+
+# The position of a single 20 x 20 item in a 100 x 100 container, for each value
+# of justify_content and align_content (same for flex and grid, except where noted)
+_SINGLE_ITEM_OFFSETS = {
+    "START": 0,
+    "END": 80,
+    "FLEX_START": 0,
+    "FLEX_END": 80,
+    "CENTER": 40,
+    "STRETCH": 0,
+    "SPACE_BETWEEN": 0,
+    "SPACE_EVENLY": 40,
+    "SPACE_AROUND": 40,
+}
+
+
+def _single_item_layout(style: Style) -> dict:
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, style.to_dict())
+    item = taffylib.node_create(taffy_ptr, Style(size=Size(20 * PT, 20 * PT)).to_dict())
+    taffylib.node_add_child(taffy_ptr, root, item)
+    taffylib.node_compute_layout(taffy_ptr, root, Size(100 * PT, 100 * PT).to_dict())
+    layout = taffylib.node_get_layout(taffy_ptr, item)
+    taffylib.free(taffy_ptr)
+    return layout
+
+
+@pytest.mark.parametrize(
+    "display, justify_content",
+    [
+        (display, value)
+        for display in (Display.FLEX, Display.GRID)
+        for value in JustifyContent
+    ],
+)
+def test_justify_content_single_item(display, justify_content):
+    layout = _single_item_layout(
+        Style(
+            display=display,
+            size=Size(100 * PT, 100 * PT),
+            justify_content=justify_content,
+            grid_template_columns=[20 * PT],
+        )
+    )
+    assert layout["left"] == _SINGLE_ITEM_OFFSETS[justify_content.name]
+    assert layout["top"] == 0
+
+
+@pytest.mark.parametrize(
+    "display, align_content",
+    [
+        (display, value)
+        for display in (Display.FLEX, Display.GRID)
+        for value in AlignContent
+    ],
+)
+def test_align_content_single_item(display, align_content):
+    layout = _single_item_layout(
+        Style(
+            display=display,
+            size=Size(100 * PT, 100 * PT),
+            flex_wrap=FlexWrap.WRAP,
+            align_content=align_content,
+            grid_template_rows=[20 * PT],
+        )
+    )
+    expected = _SINGLE_ITEM_OFFSETS[align_content.name]
+    if display == Display.FLEX and align_content in (
+        AlignContent.SPACE_EVENLY,
+        AlignContent.SPACE_AROUND,
+    ):
+        # Taffy stretches a single flex line for these (browsers center it, at
+        # 40), so the item is placed at the start of the line by align_items
+        expected = 0
+    assert layout["top"] == expected
+    assert layout["left"] == 0
+
+
+def test_auto_margin_centering():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(400 * PT, 300 * PT)).to_dict()
+    )
+    styles = [
+        # Centered horizontally
+        Style(size=Size(100 * PT, 50 * PT), margin=Rect(0, AUTO)),
+        # Centered horizontally and vertically
+        Style(size=Size(100 * PT, 50 * PT), margin=AUTO),
+        # Centered within the parent, when absolutely positioned with inset 0
+        Style(
+            position=Position.ABSOLUTE,
+            inset=0,
+            size=Size(100 * PT, 50 * PT),
+            margin=AUTO,
+        ),
+    ]
+    assert styles[1].margin.left == AUTO
+    for style in styles:
+        child = taffylib.node_create(taffy_ptr, style.to_dict())
+        taffylib.node_set_children(taffy_ptr, root, [child])
+        taffylib.node_compute_layout(
+            taffy_ptr, root, Size(400 * PT, 300 * PT).to_dict()
+        )
+        layout = taffylib.node_get_layout(taffy_ptr, child)
+        assert layout["left"] == 150
+        assert layout["top"] == (0 if style.margin.top != AUTO else 125)
+        taffylib.node_drop(taffy_ptr, child)
+    taffylib.free(taffy_ptr)
+
+
+def test_grid_track_minmax():
+    track = GridTrackSize.minmax(100, 1 * FR)
+    assert track.to_dict() == dict(
+        min_size=dict(dim=1, value=100), max_size=dict(dim=7, value=1)
+    )
+    assert GridTrackSize.minmax("100px", "1fr") == track
+    assert GridTrackSize.from_inline("minmax(100px, 1fr)") == track
+    assert str(track) == "minmax(100.00 pt, 1.00 fr)"
+    assert _same(
+        GridTrackSize.minmax(MIN_CONTENT, 50 * PCT).to_dict(),
+        dict(min_size=dict(dim=3, value=NAN), max_size=dict(dim=2, value=0.5)),
+    )
+    with pytest.raises(TypeError):
+        GridTrackSize.minmax(1 * FR, 100)
+
+    taffy_ptr = taffylib.init()
+    # The first column does not shrink below 100, even if the fr size is less
+    for width, widths in [(300, [150, 150]), (150, [100, 50])]:
+        root = taffylib.node_create(
+            taffy_ptr,
+            Style(
+                display=Display.GRID,
+                size=Size(width * PT, 100 * PT),
+                grid_template_columns=[track, "1fr"],
+            ).to_dict(),
+        )
+        children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+        taffylib.node_set_children(taffy_ptr, root, children)
+        taffylib.node_compute_layout(
+            taffy_ptr, root, Size(width * PT, 100 * PT).to_dict()
+        )
+        layouts = [taffylib.node_get_layout(taffy_ptr, child) for child in children]
+        assert [layout["width"] for layout in layouts] == widths
+    taffylib.free(taffy_ptr)
+
+
+def test_grid_track_fit_content():
+    track = GridTrackSize.fit_content(200)
+    assert track.to_dict()["max_size"] == dict(dim=5, value=200)
+    assert GridTrackSize.from_inline("fit-content(200px)") == track
+    track = GridTrackSize.fit_content(50 * PCT)
+    assert track.to_dict()["max_size"] == dict(dim=6, value=0.5)
+    with pytest.raises(TypeError):
+        GridTrackSize.fit_content(None)
+
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr,
+        Style(
+            display=Display.GRID,
+            size=Size(1000 * PT, 100 * PT),
+            grid_template_columns=[GridTrackSize.fit_content(200), "1fr"],
+        ).to_dict(),
+    )
+    children = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    taffylib.node_set_children(taffy_ptr, root, children)
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        # Text which is 500 wide on a single line, and 50 wide when wrapped
+        if known_width == known_width:
+            return (known_width, 10 * -(-500 // known_width))
+        if available_width["dim"] == 3:
+            return (50, 100)
+        return (500, 10)
+
+    taffylib.node_set_measure(taffy_ptr, children[0], children[0], measure)
+    taffylib.node_compute_layout(
+        taffy_ptr, root, Size(1000 * PT, 100 * PT).to_dict(), strict=True
+    )
+    # The column is limited to 200, rather than the 500 the content could use
+    assert taffylib.node_get_layout(taffy_ptr, children[0])["width"] == 200
+    assert taffylib.node_get_layout(taffy_ptr, children[1])["left"] == 200
+    taffylib.free(taffy_ptr)
+
+
+def test_grid_named_areas():
+    assert GridPlacement.from_any("main").to_dict() == dict(
+        start=dict(kind=1, value=0, name="main"), end=dict(kind=1, value=0, name="main")
+    )
+    assert GridPlacement.from_any("a / 3").end == GridIndex.from_index(3)
+    with pytest.raises(ValueError, match="not a rectangle"):
+        Style(grid_template_areas=["a b", "b a"]).to_dict()
+    style = Style.from_css("display: grid; grid-template-areas: 'a a' \"b .\"")
+    assert style.grid_template_areas == ["a a", "b ."]
+
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr,
+        Style(
+            display=Display.GRID,
+            grid_template_rows=[50, 50],
+            grid_template_columns=[100, 100],
+            grid_template_areas=["a b", "c d"],
+        ).to_dict(),
+    )
+    # Placed by area name, in reverse order of the areas
+    areas = ["d", "c", "b", "a"]
+    children = taffylib.nodes_create(
+        taffy_ptr,
+        [Style(grid_row=area, grid_column=area).to_dict() for area in areas],
+    )
+    taffylib.node_set_children(taffy_ptr, root, children)
+    style = taffylib.node_get_style(taffy_ptr, children[0])
+    assert style["grid_row"]["start"] == dict(kind=1, value=0, name="d")
+    assert taffylib.node_get_style(taffy_ptr, root)["grid_template_row_names"] == {
+        "a-start": 1,
+        "a-end": 2,
+        "b-start": 1,
+        "b-end": 2,
+        "c-start": 2,
+        "c-end": 3,
+        "d-start": 2,
+        "d-end": 3,
+    }
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 100 * PT).to_dict())
+    layouts = [taffylib.node_get_layout(taffy_ptr, child) for child in children]
+    assert [(layout["left"], layout["top"]) for layout in layouts] == [
+        (100, 50),
+        (0, 50),
+        (100, 0),
+        (0, 0),
+    ]
+    assert all((layout["width"], layout["height"]) == (100, 50) for layout in layouts)
+
+    # Start and end by line name, which are resolved again when the names change
+    names = dict(left=1, middle=2, right=3)
+    style = Style(
+        display=Display.GRID,
+        grid_template_rows=[50, 50],
+        grid_template_columns=[100, 100],
+        grid_template_column_names=names,
+    )
+    taffylib.node_set_style(taffy_ptr, root, style.to_dict())
+    taffylib.node_set_style(
+        taffy_ptr,
+        children[0],
+        Style(grid_row="1", grid_column="left / right").to_dict(),
+    )
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 100 * PT).to_dict())
+    layout = taffylib.node_get_layout(taffy_ptr, children[0])
+    assert (layout["left"], layout["top"], layout["width"]) == (0, 0, 200)
+    names = dict(left=2, right=3)
+    style = Style(
+        display=Display.GRID,
+        grid_template_rows=[50, 50],
+        grid_template_columns=[100, 100],
+        grid_template_column_names=names,
+    )
+    taffylib.node_set_style(taffy_ptr, root, style.to_dict())
+    taffylib.node_compute_layout(taffy_ptr, root, Size(200 * PT, 100 * PT).to_dict())
+    layout = taffylib.node_get_layout(taffy_ptr, children[0])
+    assert (layout["left"], layout["width"]) == (100, 100)
+    taffylib.free(taffy_ptr)
+
+
+def test_node_set_measure_context():
+    class Text(str):
+        pass
+
+    def measure(text, known_width, known_height, available_width, available_height):
+        return (len(text) * 10, 20)
+
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    nodes = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 2)
+    taffylib.node_set_children(taffy_ptr, root, nodes)
+    texts = [Text("Hello"), Text("Hello, world")]
+    for node, text in zip(nodes, texts):
+        taffylib.node_set_measure(taffy_ptr, node, text, measure)
+    taffylib.node_compute_layout(
+        taffy_ptr, root, Size(1000 * PT, 1000 * PT).to_dict(), strict=True
+    )
+    widths = [taffylib.node_get_layout(taffy_ptr, node)["width"] for node in nodes]
+    assert widths == [50, 120]
+
+    # The references to the context are released with the measure function
+    refs = [weakref.ref(text) for text in texts]
+    del text, texts
+    assert all(ref() is not None for ref in refs)
+    taffylib.node_remove_measure(taffy_ptr, nodes[0])
+    assert refs[0]() is None
+    taffylib.node_drop(taffy_ptr, nodes[1])
+    assert refs[1]() is None
+    taffylib.free(taffy_ptr)
+
+
+def test_node_set_measure_repeated_layouts():
+    class Measure:
+        def __init__(self):
+            self.calls = 0
+
+        def __call__(self, context, known_width, known_height, width, height):
+            self.calls += 1
+            return (width["value"] / 2, 10)
+
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(taffy_ptr, Style().to_dict())
+    text = taffylib.node_create(taffy_ptr, Style().to_dict())
+    taffylib.node_add_child(taffy_ptr, root, text)
+    measure = Measure()
+    context = Measure()
+    taffylib.node_set_measure(taffy_ptr, text, context, measure)
+
+    # The measure function is kept on the node, so it is only set once
+    for width in (100, 200, 300):
+        available_space = Size(width * PT, 100 * PT).to_dict()
+        taffylib.node_compute_layout(taffy_ptr, root, available_space)
+        assert taffylib.node_get_layout(taffy_ptr, text)["width"] == width / 2
+    assert measure.calls >= 3
+
+    # The references are released when the tree is freed
+    refs = [weakref.ref(measure), weakref.ref(context)]
+    del measure, context
+    assert all(ref() is not None for ref in refs)
+    taffylib.free(taffy_ptr)
+    assert all(ref() is None for ref in refs)
+
+
+def test_node_set_measure_cached():
+    taffy_ptr = taffylib.init()
+    text = taffylib.node_create(taffy_ptr, Style().to_dict())
+    node = text
+    for direction in [FlexDirection.COLUMN, FlexDirection.ROW, FlexDirection.COLUMN]:
+        parent = taffylib.node_create(
+            taffy_ptr, Style(flex_direction=direction).to_dict()
+        )
+        taffylib.node_add_child(taffy_ptr, parent, node)
+        node = parent
+    calls = []
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        calls.append(
+            (
+                known_width,
+                known_height,
+                tuple(available_width.values()),
+                tuple(available_height.values()),
+            )
+        )
+        return (120 if known_width != known_width else known_width, 20)
+
+    taffylib.node_set_measure(taffy_ptr, text, text, measure)
+    available_space = Size(300 * PT, 300 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, node, available_space)
+    # Taffy measures the node twice with the same arguments in this layout
+    assert len(calls) == len(set(str(call) for call in calls)) == 7
+
+    # The cache is not used for the next computation
+    taffylib.node_mark_dirty(taffy_ptr, text)
+    taffylib.node_compute_layout(taffy_ptr, node, available_space)
+    assert len(calls) == 14
+    taffylib.free(taffy_ptr)
+
+
+def test_node_set_measure_previous():
+    taffy_ptr = taffylib.init()
+    node = taffylib.node_create(taffy_ptr, Style().to_dict())
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        return (10, 10)
+
+    assert taffylib.node_set_measure(taffy_ptr, node, node, measure) is False
+    assert taffylib.node_set_measure(taffy_ptr, node, node, measure) is True
+    assert taffylib.node_remove_measure(taffy_ptr, node) is True
+    assert taffylib.node_remove_measure(taffy_ptr, node) is False
+    with pytest.raises(KeyError):
+        taffylib.node_set_measure(taffy_ptr, 999999, None, measure)
+    taffylib.free(taffy_ptr)
+
+
+def test_nodes_set_measure():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(flex_direction=FlexDirection.COLUMN).to_dict()
+    )
+    nodes = taffylib.nodes_create(taffy_ptr, [Style().to_dict()] * 50)
+    taffylib.node_set_children(taffy_ptr, root, nodes)
+    available_space = Size(100 * PT, 1000 * PT).to_dict()
+
+    measured = set()
+
+    def measure(node, known_width, known_height, available_width, available_height):
+        measured.add(node)
+        return (10, 10)
+
+    taffylib.nodes_set_measure(taffy_ptr, nodes, measure, nodes)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert measured == set(nodes)
+    assert taffylib.node_get_layout(taffy_ptr, root)["height"] == 500
+
+    # Without contexts, the nodes are measured with None as the context (only
+    # the changed nodes are measured again)
+    measured.clear()
+    taffylib.nodes_set_measure(taffy_ptr, nodes[:10], measure)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert measured == {None}
+
+    # Removes the measure functions
+    measured.clear()
+    taffylib.nodes_set_measure(taffy_ptr, nodes, None)
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert not measured
+    assert taffylib.node_get_layout(taffy_ptr, root)["height"] == 0
+
+    # Nothing is changed if any node is invalid
+    with pytest.raises(KeyError):
+        taffylib.nodes_set_measure(taffy_ptr, [nodes[0], 999999], measure)
+    assert taffylib.node_remove_measure(taffy_ptr, nodes[0]) is False
+    with pytest.raises(ValueError, match="expected 2 contexts, got 1"):
+        taffylib.nodes_set_measure(taffy_ptr, nodes[:2], measure, [None])
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize("aspect_ratio", [0.0, -1.5, NAN, float("inf"), float("-inf")])
+def test_invalid_aspect_ratio(aspect_ratio):
+    taffy_ptr = taffylib.init()
+    style = Style(aspect_ratio=aspect_ratio).to_dict()
+    with pytest.raises(ValueError, match="aspect_ratio must be positive and finite"):
+        taffylib.node_create(taffy_ptr, style)
+    with pytest.raises(ValueError, match="aspect_ratio"):
+        taffylib.nodes_create(taffy_ptr, [Style().to_dict(), style])
+    assert taffylib.total_node_count(taffy_ptr) == 0
+
+    node = taffylib.node_create(taffy_ptr, Style(aspect_ratio=2.0).to_dict())
+    with pytest.raises(ValueError, match="aspect_ratio"):
+        taffylib.node_set_style(taffy_ptr, node, style)
+    assert taffylib.node_get_style(taffy_ptr, node)["aspect_ratio"] == 2.0
+
+    tree = json.loads(taffylib.tree_to_json(taffy_ptr, node))
+    tree["children"] = [dict(style=style, children=[])]
+    with pytest.raises(ValueError, match="aspect_ratio"):
+        taffylib.tree_from_json(taffy_ptr, json.dumps(tree))
+    assert taffylib.total_node_count(taffy_ptr) == 1
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize(
+    "style, field",
+    [
+        (Style(gap=Size(-10 * PT, 10 * PT)), "gap.width"),
+        (Style(gap=Size(0, -5 * PCT)), "gap.height"),
+        (Style(padding=Rect(0, 0, 0, -1 * PT)), "padding.left"),
+        (Style(border=Rect(-2 * PCT, 0, 0, 0)), "border.top"),
+    ],
+)
+def test_negative_spacing(style, field):
+    taffy_ptr = taffylib.init()
+    with pytest.raises(ValueError, match=rf"{field} must not be negative"):
+        taffylib.node_create(taffy_ptr, style.to_dict())
+    node = taffylib.node_create(taffy_ptr, Style(gap=10 * PT).to_dict())
+    with pytest.raises(ValueError, match=field):
+        taffylib.node_set_style(taffy_ptr, node, style.to_dict())
+    assert taffylib.node_get_style(taffy_ptr, node)["gap"]["width"]["value"] == 10
+    taffylib.free(taffy_ptr)
+
+
+@pytest.mark.parametrize("value", [float("inf"), float("-inf"), NAN])
+def test_non_finite_length(value):
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    style["size"]["width"] = {"dim": 1, "value": value}
+    with pytest.raises(ValueError, match="size.width must be finite"):
+        taffylib.node_create(taffy_ptr, style)
+
+    style = Style(padding=Rect(0, 0, 0, value * PCT)).to_dict()
+    with pytest.raises(ValueError, match="padding.left must be finite"):
+        taffylib.node_create(taffy_ptr, style)
+    style = Style(grid_template_columns=[1 * FR, value * FR]).to_dict()
+    with pytest.raises(ValueError, match=r"grid_template_columns\[1\]\.max_size"):
+        taffylib.node_create(taffy_ptr, style)
+    assert taffylib.total_node_count(taffy_ptr) == 0
+
+    # Zero is valid, as are dimensions without a value (with NAN as the value)
+    style = Style(size=Size(0 * PT, AUTO), margin=0 * PCT).to_dict()
+    assert taffylib.node_create(taffy_ptr, style)
+    taffylib.free(taffy_ptr)
+
+
+def test_calc_size():
+    taffy_ptr = taffylib.init()
+    root = taffylib.node_create(
+        taffy_ptr, Style(size=Size(200 * PT, 100 * PT), padding=10 * PT).to_dict()
+    )
+    child = taffylib.node_create(
+        taffy_ptr, Style(size=Size(calc(100 * PCT, -20), 50 * PT)).to_dict()
+    )
+    grandchild = taffylib.node_create(
+        taffy_ptr,
+        Style(size=Size(calc(50 * PCT, 5 * PT), 10 * PT), flex_shrink=0).to_dict(),
+    )
+    taffylib.node_set_children(taffy_ptr, root, [child])
+    taffylib.node_set_children(taffy_ptr, child, [grandchild])
+    available_space = Size(400 * PT, 400 * PT).to_dict()
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert taffylib.node_get_size(taffy_ptr, child) == (160.0, 50.0)
+    assert taffylib.node_get_size(taffy_ptr, grandchild) == (85.0, 10.0)
+
+    # The calc lengths are kept, rather than the lengths they resolve to
+    style = taffylib.node_get_style(taffy_ptr, child)
+    assert style["size"]["width"] == dict(dim=8, value=1.0, offset=-20.0)
+    assert "offset" not in style["size"]["height"]
+    clone = taffylib.tree_from_json(taffy_ptr, taffylib.tree_to_json(taffy_ptr, child))
+    assert taffylib.node_get_style(taffy_ptr, clone)["size"] == style["size"]
+
+    taffylib.node_set_style(
+        taffy_ptr, root, Style(size=Size(300 * PT, 100 * PT), padding=10 * PT).to_dict()
+    )
+    taffylib.node_compute_layout(taffy_ptr, root, available_space)
+    assert taffylib.node_get_size(taffy_ptr, child) == (260.0, 50.0)
+    assert taffylib.node_get_size(taffy_ptr, grandchild) == (135.0, 10.0)
+    taffylib.free(taffy_ptr)
+
+
+def test_calc_invalid():
+    with pytest.raises(TypeError):
+        calc(10 * PT, 5)
+    with pytest.raises(TypeError):
+        Style(margin=calc(100 * PCT, -20))
+    assert str(calc(100 * PCT, -20)) == "calc(100.00 % - 20.00 pt)"
+
+    taffy_ptr = taffylib.init()
+    style = Style().to_dict()
+    style["flex_basis"] = calc(100 * PCT, -20).to_dict()
+    with pytest.raises(ValueError, match="calc lengths are only supported"):
+        taffylib.node_create(taffy_ptr, style)
+    taffylib.free(taffy_ptr)
+
+
+def test_style_from_css():
+    style = Style.from_css(
+        "display:flex; flex-direction:column; gap:10px; padding:8px 16px"
+    )
+    assert style == Style(
+        display=Display.FLEX,
+        flex_direction=FlexDirection.COLUMN,
+        gap=10 * PT,
+        padding=Rect(8 * PT, 16 * PT),
+    )
+
+    # Shorthands with 1-4 values, property names are case-insensitive
+    style = Style.from_css(
+        ".box { Margin: 1px 2px 3px 4px; inset: 5%; border-width: 1px 2px 3px; }"
+    )
+    assert style.margin == Rect(top=1, right=2, bottom=3, left=4)
+    assert style.inset == Rect(5 * PCT)
+    assert style.border == Rect(top=1, right=2, bottom=3, left=2)
+
+    style = Style.from_css(
+        "position: absolute; align-items: flex-end; justify-content: space-between"
+    )
+    assert style.position == Position.ABSOLUTE
+    assert style.align_items == AlignItems.FLEX_END
+    assert style.justify_content == JustifyContent.SPACE_BETWEEN
+
+    with pytest.raises(ValueError, match="color is not recognized"):
+        Style.from_css("display: flex; color: red")
+    with pytest.raises(ValueError, match="display: table is not supported"):
+        Style.from_css("display: table")
+    # `from_inline` ignores them instead
+    assert Style.from_inline("display: grid; color: red").display == Display.GRID